@@ -1,11 +1,8 @@
 mod ants;
 
-use ants::ant::Ant;
 use ants::bot::Bot;
 use ants::direction::Direction;
 use ants::error::Result;
-use ants::player::Player;
-use ants::tile::Tile;
 use ants::world::World;
 
 struct MyBot;
@@ -13,15 +10,10 @@ struct MyBot;
 impl Bot for MyBot {
     fn do_turn(&mut self, world: &mut World) -> Result<()> {
         let direction = Direction::North;
-        for point in world.map.tiles().filter_map(|(point, &tile)| {
-                    if tile == Some(Tile::Ant(Ant{ alive: true, owner: Player::Me })) {
-                        Some(point)
-                    } else {
-                        None
-                    }
-                }) {
-            if world.map[point + direction].unwrap_or(Tile::Land).is_passable() {
-                world.order(point, direction);
+        let ants = world.my_ants().to_vec();
+        for point in ants {
+            if world.map[point + direction].is_passable() {
+                try!(world.order(point, direction));
             }
         }
         Ok(())
@@ -0,0 +1,362 @@
+//! Local search for the best moves in small fights.
+//!
+//! Our ants near the enemy are split into separate engagements. For each,
+//! we look for the moves which do best against the worst of a few likely
+//! enemy responses, scoring every outcome with the combat resolver by the
+//! ants killed and lost. The search improves one ant's move at a time and
+//! stops at the turn's deadline, so it always has a plan to give.
+
+use std::collections::HashMap;
+use ants::clock::Clock;
+use ants::combat;
+use ants::direction::Direction;
+use ants::map::{Map, UnknownPolicy};
+use ants::offsets::OffsetSet;
+use ants::order::Order;
+use ants::params::Params;
+use ants::player::Player;
+use ants::point::Point;
+
+/// A group of our ants and the enemies they could be fighting next turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Engagement {
+    pub ours: Vec<Point>,
+    pub enemies: Vec<(Point, Player)>,
+}
+
+/// One move for each ant in a group, `None` to stay put.
+type Plan = Vec<Option<Direction>>;
+
+/// How well a plan does: its score, then fewer of our ants lost.
+type Outcome = (i32, i32);
+
+/// Searches for the moves which do best in fights with the enemy.
+#[derive(Debug, Clone)]
+pub struct Battle<'a> {
+    /// Value of each enemy ant killed.
+    pub kill_value: i32,
+    /// Cost of each of our ants lost.
+    pub loss_value: i32,
+    map: &'a Map,
+    params: &'a Params,
+    reach: OffsetSet,
+}
+
+impl<'a> Battle<'a> {
+    /// Create a search over `map`, valuing kills and losses equally.
+    pub fn new(map: &'a Map, params: &'a Params) -> Battle<'a> {
+        Battle {
+            kill_value: 1,
+            loss_value: 1,
+            map: map,
+            params: params,
+            // both sides get one move before the fight
+            reach: OffsetSet::new(params.attackradius2).expand().expand(),
+        }
+    }
+
+    /// Split our ants and the enemy's into separate engagements.
+    ///
+    /// Ants are in the same engagement if they are linked by ants of ours
+    /// and the enemy's which could be in attack range of each other after
+    /// both have moved. Ants out of reach of the other side are left out.
+    pub fn engagements(&self, ours: &[Point], enemies: &[(Point, Player)])
+            -> Vec<Engagement> {
+        let (rows, cols) = (self.params.rows, self.params.cols);
+        let mut groups: Vec<usize> = (0..ours.len() + enemies.len()).collect();
+        for (i, &a) in ours.iter().enumerate() {
+            for (j, &(b, _)) in enemies.iter().enumerate() {
+                if self.reach.contains(a.delta(b, rows, cols)) {
+                    let (x, y) = (root(&groups, i),
+                                  root(&groups, ours.len() + j));
+                    groups[x.max(y)] = x.min(y);
+                }
+            }
+        }
+        let mut engagements = Vec::new();
+        let mut index = HashMap::new();
+        for i in 0..groups.len() {
+            let group = root(&groups, i);
+            let k = *index.entry(group).or_insert_with(|| {
+                engagements.push(Engagement {
+                    ours: Vec::new(),
+                    enemies: Vec::new(),
+                });
+                engagements.len() - 1
+            });
+            if i < ours.len() {
+                engagements[k].ours.push(ours[i]);
+            } else {
+                engagements[k].enemies.push(enemies[i - ours.len()]);
+            }
+        }
+        engagements.retain(|e| !e.ours.is_empty() && !e.enemies.is_empty());
+        engagements
+    }
+
+    /// Search every engagement between `ours` and `enemies` in turn.
+    ///
+    /// All searches share `clock`, so later engagements get less time.
+    pub fn plan(&self, ours: &[Point], enemies: &[(Point, Player)],
+                clock: &Clock) -> Vec<Order> {
+        let mut orders = Vec::new();
+        for engagement in self.engagements(ours, enemies) {
+            orders.extend(self.search(&engagement, clock));
+        }
+        orders
+    }
+
+    /// Find the best moves for our ants in `engagement`.
+    ///
+    /// The enemy may stay put, advance on our nearest ant or back away
+    /// from it; a plan is only as good as its worst result against these.
+    /// Starting from the best of staying, advancing and retreating
+    /// ourselves, each ant's move is changed while that improves the plan
+    /// and `clock` has time left. Our moves never collide with each other.
+    ///
+    /// Returns orders for the ants which should move; the rest stay put.
+    pub fn search(&self, engagement: &Engagement, clock: &Clock)
+            -> Vec<Order> {
+        let ours = &engagement.ours;
+        let enemies: Vec<Point> = engagement.enemies.iter()
+            .map(|&(point, _)| point).collect();
+        let responses = [
+            vec![None; enemies.len()],
+            self.approach(&enemies, ours, false),
+            self.approach(&enemies, ours, true),
+        ];
+        let candidates = [
+            vec![None; ours.len()],
+            self.approach(ours, &enemies, false),
+            self.approach(ours, &enemies, true),
+        ];
+        let mut best = None;
+        for plan in candidates.iter() {
+            let outcome = self.worst_case(engagement, plan, &responses);
+            match best {
+                Some((_, best_outcome)) if best_outcome >= outcome => {},
+                _ => best = Some((plan.clone(), outcome)),
+            }
+        }
+        let (mut plan, mut outcome) = best.unwrap();
+
+        let mut improved = true;
+        'search: while improved {
+            improved = false;
+            for i in 0..ours.len() {
+                let current = plan[i];
+                let moves = Some(None).into_iter()
+                    .chain(Direction::ALL.iter().map(|&d| Some(d)));
+                for direction in moves {
+                    if clock.is_out_of_time() {
+                        break 'search;
+                    }
+                    if direction == plan[i] ||
+                            !self.can_move(ours[i], direction) ||
+                            self.collides(ours, &plan, i, direction) {
+                        continue;
+                    }
+                    let previous = plan[i];
+                    plan[i] = direction;
+                    let next = self.worst_case(engagement, &plan, &responses);
+                    if next > outcome {
+                        outcome = next;
+                    } else {
+                        plan[i] = previous;
+                    }
+                }
+                improved |= plan[i] != current;
+            }
+        }
+
+        ours.iter().zip(plan.iter())
+            .filter_map(|(&from, &direction)| direction.map(|direction| {
+                Order { from: from, direction: direction }
+            }))
+            .collect()
+    }
+
+    /// The worst outcome of `plan` against each of the enemy's `responses`.
+    fn worst_case(&self, engagement: &Engagement, plan: &Plan,
+                  responses: &[Plan]) -> Outcome {
+        responses.iter()
+            .map(|response| self.outcome(engagement, plan, response))
+            .min()
+            .unwrap()
+    }
+
+    /// Play out our `plan` against the enemy's `response`.
+    ///
+    /// Ants which end up on the same square die, as in the game, and the
+    /// rest fight under the focus rule.
+    fn outcome(&self, engagement: &Engagement, plan: &Plan, response: &Plan)
+            -> Outcome {
+        let ours = engagement.ours.iter().zip(plan.iter())
+            .map(|(&point, &direction)| (point, direction, Player::Me));
+        let enemies = engagement.enemies.iter().zip(response.iter())
+            .map(|(&(point, owner), &direction)| (point, direction, owner));
+        let moved: Vec<(Point, Player)> = ours.chain(enemies)
+            .map(|(point, direction, owner)| {
+                (self.destination(point, direction), owner)
+            })
+            .collect();
+
+        let mut counts = HashMap::new();
+        for &(point, _) in moved.iter() {
+            *counts.entry(point).or_insert(0) += 1;
+        }
+        let (collided, fighting): (Vec<_>, Vec<_>) = moved.into_iter()
+            .partition(|&(point, _)| counts[&point] > 1);
+        let dead = combat::resolve(&fighting, self.params);
+        let dying = collided.iter().map(|&(_, owner)| owner)
+            .chain(fighting.iter().zip(dead.iter())
+                   .filter(|&(_, &dies)| dies)
+                   .map(|(&(_, owner), _)| owner));
+
+        let (mut lost, mut killed) = (0, 0);
+        for owner in dying {
+            if owner == Player::Me {
+                lost += 1;
+            } else {
+                killed += 1;
+            }
+        }
+        (self.kill_value * killed - self.loss_value * lost, -lost)
+    }
+
+    /// Move each of `ants` one step toward the nearest of `targets`, or
+    /// away from it when `retreat` is set.
+    ///
+    /// Ants with no free move stay put, as do any which would run into
+    /// each other.
+    fn approach(&self, ants: &[Point], targets: &[Point], retreat: bool)
+            -> Plan {
+        let (rows, cols) = (self.params.rows, self.params.cols);
+        let mut plan: Plan = ants.iter().map(|&ant| {
+            let nearest = targets.iter()
+                .min_by_key(|&&target| ant.distance2(target, rows, cols));
+            let directions = match nearest {
+                Some(&target) => ant.directions_toward(target, rows, cols),
+                None => Vec::new(),
+            };
+            directions.into_iter()
+                .map(|d| if retreat { d.opposite() } else { d })
+                .find(|&d| self.can_move(ant, Some(d)))
+        }).collect();
+
+        // a mover whose square is taken stays instead, which may in turn
+        // take another's square
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..ants.len() {
+                if plan[i].is_some() &&
+                        self.collides(ants, &plan, i, plan[i]) {
+                    plan[i] = None;
+                    changed = true;
+                }
+            }
+        }
+        plan
+    }
+
+    /// Whether the ant at `from` could make the move `direction`.
+    fn can_move(&self, from: Point, direction: Option<Direction>) -> bool {
+        let to = self.destination(from, direction);
+        self.map.is_walkable(to, UnknownPolicy::Passable)
+    }
+
+    /// Whether moving ant `i` of `ants` in `direction` would put it on the
+    /// same square as another ant following `plan`.
+    fn collides(&self, ants: &[Point], plan: &Plan, i: usize,
+                direction: Option<Direction>) -> bool {
+        let to = self.destination(ants[i], direction);
+        ants.iter().zip(plan.iter()).enumerate()
+            .any(|(j, (&ant, &other))| {
+                j != i && self.destination(ant, other) == to
+            })
+    }
+
+    /// Where an ant at `from` ends up after the move `direction`.
+    fn destination(&self, from: Point, direction: Option<Direction>)
+            -> Point {
+        let (rows, cols) = (self.params.rows, self.params.cols);
+        match direction {
+            Some(direction) => (from + direction).wrap(rows, cols),
+            None => from,
+        }
+    }
+}
+
+/// The representative of the group containing `i`.
+fn root(groups: &[usize], mut i: usize) -> usize {
+    while groups[i] != i {
+        i = groups[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use ants::tile::Tile;
+
+    fn setup() -> (Map, Params) {
+        let mut params = Params::new();
+        params.rows = 20;
+        params.cols = 20;
+        params.attackradius2 = 5;
+        let map = Map::filled(20, 20, Tile::land());
+        (map, params)
+    }
+
+    fn point(row: i32, col: i32) -> Point {
+        Point { row: row, col: col }
+    }
+
+    #[test]
+    fn engagements() {
+        let (map, params) = setup();
+        let battle = Battle::new(&map, &params);
+        let ours = [point(2, 2), point(2, 4), point(12, 12)];
+        let enemies = [(point(2, 7), Player::Other(1)),
+                       (point(16, 16), Player::Other(1))];
+        assert_eq!(battle.engagements(&ours, &enemies), vec![Engagement {
+            ours: vec![point(2, 4)],
+            enemies: vec![(point(2, 7), Player::Other(1))],
+        }]);
+    }
+
+    #[test]
+    fn retreats_when_outnumbered() {
+        let (map, params) = setup();
+        let battle = Battle::new(&map, &params);
+        let clock = Clock::new(Duration::from_secs(10));
+        let ours = [point(10, 10)];
+        let enemies = [(point(10, 13), Player::Other(1)),
+                       (point(11, 13), Player::Other(1))];
+        assert_eq!(battle.plan(&ours, &enemies, &clock), vec![Order {
+            from: point(10, 10),
+            direction: Direction::West,
+        }]);
+    }
+
+    #[test]
+    fn out_of_time() {
+        // with no time to search, the plan is still free of collisions
+        let (map, params) = setup();
+        let battle = Battle::new(&map, &params);
+        let clock = Clock::new(Duration::from_millis(0));
+        let ours = [point(10, 10), point(10, 11), point(11, 10)];
+        let enemies = [(point(10, 14), Player::Other(1))];
+        let orders = battle.plan(&ours, &enemies, &clock);
+        let mut destinations: Vec<Point> = ours.iter().map(|&ant| {
+            orders.iter().find(|o| o.from == ant)
+                .map_or(ant, |o| o.to().wrap(20, 20))
+        }).collect();
+        destinations.sort();
+        destinations.dedup();
+        assert_eq!(destinations.len(), ours.len());
+    }
+}
@@ -1,9 +1,17 @@
 //! Home of `Bot` trait, where user specifies their bot's unique behavior.
 
-use std::io::{stdin, BufRead};
+use std::cell::Cell;
+use std::io::{self, stdin, stdout, BufRead, BufReader, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
 use ants::world::World;
-use ants::error::Result;
+use ants::error::{Result, Error};
 use ants::params::Params;
+use ants::game_result::GameResult;
+use ants::order::Order;
 
 /// The `Bot` describes the unique functionality of all bots.
 ///
@@ -27,24 +35,478 @@ pub trait Bot {
     /// turn begins; If it is `Err(..)`, the bot will exit.
     fn do_turn(&mut self, world: &mut World) -> Result<()>;
 
+    /// One time setup logic, called before the first turn.
+    ///
+    /// By the time it is called, the game parameters are known and the
+    /// world's clock is running against `loadtime`. The default
+    /// implementation does nothing.
+    fn do_setup(&mut self, world: &mut World) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once after the game has ended, with the final scores.
+    ///
+    /// By the time it is called, the final map update has been applied to
+    /// `world`. The default implementation does nothing.
+    fn on_game_end(&mut self, world: &World, result: &GameResult) {}
+
     /// The glue logic which maintains the world and calls `do_turn`.
     ///
-    /// The default implementation should be sufficient for most purposes.
+    /// Talks to the engine over stdin and stdout; see `run_with`.
     fn run(&mut self) -> Result<()> {
         let stdin = stdin();
-        let params = try!(Params::from_buf_read(stdin.lock()));
-        let mut world = World::new(&params);
-        println!("go");
-        for line in stdin.lock().lines() {
+        self.run_with(stdin.lock(), stdout())
+    }
+
+    /// The glue logic, reading engine input from `input` and writing orders
+    /// to `output`.
+    ///
+    /// This allows driving a bot from something other than a real engine,
+    /// such as a replay file or an in-process test harness. The default
+    /// implementation should be sufficient for most purposes.
+    fn run_with<R: BufRead, W: Write>(&mut self, input: R, output: W)
+            -> Result<()> {
+        play(self, input, output, false, |bot, world| {
+            try!(bot.do_turn(world));
+            world.go()
+        })
+    }
+
+    /// Like `run`, but recovers from errors where possible.
+    ///
+    /// See `run_resilient_with`.
+    fn run_resilient(&mut self) -> Result<()> {
+        let stdin = stdin();
+        self.run_resilient_with(stdin.lock(), stdout())
+    }
+
+    /// Like `run_with`, but recovers from errors where possible.
+    ///
+    /// Malformed turn input lines are skipped. Errors returned from
+    /// `do_setup` or `do_turn`, and panics inside them, end that phase
+    /// early: whatever orders were queued are sent along with "go", and the
+    /// game carries on. Each such error is logged to stderr and recorded in
+    /// `World::recovered_errors`, so it can be reviewed in `on_game_end`.
+    ///
+    /// Errors in the game parameters or while talking to the engine are
+    /// still fatal.
+    fn run_resilient_with<R: BufRead, W: Write>(&mut self, input: R,
+                                                 output: W)
+            -> Result<()> {
+        play(self, input, output, true, |bot, world| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                bot.do_turn(world)
+            }));
+            if let Err(error) = flatten(result) {
+                recover(world, error);
+            }
+            world.go()
+        })
+    }
+
+    /// Called when `do_turn` overran the deadline under `run_guarded`.
+    ///
+    /// By the time it is called, `do_turn` has finished, a fallback turn has
+    /// been sent in its place and its late orders have been discarded.
+    /// `elapsed` is how long it actually took. The default implementation
+    /// does nothing.
+    fn on_overrun(&mut self, world: &World, elapsed: Duration) {}
+
+    /// Like `run`, but guards each turn against the `turntime` deadline.
+    ///
+    /// See `run_guarded_with`.
+    fn run_guarded(&mut self) -> Result<()> {
+        self.run_guarded_with(BufReader::new(stdin()), stdout())
+    }
+
+    /// Like `run_with`, but guards each turn against the `turntime` deadline.
+    ///
+    /// Input is read on a separate thread, which notes when each "go" line
+    /// arrives. If `do_turn` has not finished by the clock's deadline
+    /// (`turntime` less the safety margin), that thread sends the orders
+    /// queued so far along with "go" so that the engine does not time the
+    /// bot out. Once `do_turn` does finish, its late orders are discarded
+    /// and `on_overrun` is called. Turns whose deadline passes while an
+    /// earlier `do_turn` is still running are skipped, and each turn's clock
+    /// starts when its "go" line arrived, so one slow turn does not make the
+    /// next ones late too.
+    ///
+    /// The input thread is not joined, so `input` and `output` must be
+    /// `'static`; it ends once the input does.
+    fn run_guarded_with<R, W>(&mut self, input: R, output: W) -> Result<()>
+            where R: BufRead + Send + 'static, W: Write + Send + 'static {
+        let output = Arc::new(Mutex::new(output));
+        let mut lines = input.lines();
+        let mut number = 0;
+        let params = try!(Params::from_lines((&mut lines).inspect(|_| {
+            number += 1
+        })));
+        let mut world = World::new(&params, Shared(output.clone()));
+        try!(self.do_setup(&mut world));
+        try!(world.go());
+
+        let state = Arc::new((Mutex::new(Watch {
+            turn: 0,
+            answered: true,
+            orders: None,
+            margin: world.clock().margin(),
+        }), Condvar::new()));
+        let (sender, received) = mpsc::channel();
+        {
+            let state = state.clone();
+            let turntime = Duration::from_millis(params.turntime.max(0)
+                                                 as u64);
+            thread::spawn(move || {
+                watch(lines, &*output, &*state, turntime, sender)
+            });
+        }
+        let &(ref watch, ref answered) = &*state;
+
+        let mut result = None;
+        let mut turn = 0;
+        for (line, arrived) in received {
+            number += 1;
             let line = try!(line);
-            if line == "go" {
-                try!(self.do_turn(&mut world));
-                println!("go");
-                world.clear();
-            } else if line.len() > 0 {
-                try!(world.update(&*line));
+            if line != "go" {
+                try!(update(&mut world, &mut result, &*line)
+                     .map_err(|e| e.at_line(number)));
+                continue;
+            }
+            if let Some(ref result) = result {
+                self.on_game_end(&world, result);
+                return Ok(());
+            }
+            turn += 1;
+            world.begin_turn_at(arrived);
+            let skipped = {
+                let mut watch = watch.lock().unwrap();
+                if watch.turn == turn && !watch.answered {
+                    watch.orders = Some(world.watch_orders());
+                    watch.margin = world.clock().margin();
+                    false
+                } else {
+                    true
+                }
+            };
+            if !skipped {
+                let outcome = self.do_turn(&mut world);
+                let mut watch = watch.lock().unwrap();
+                world.unwatch_orders();
+                if watch.turn != turn || watch.answered {
+                    drop(watch);
+                    world.discard_orders();
+                    let elapsed = world.clock().time_elapsed();
+                    self.on_overrun(&world, elapsed);
+                    try!(outcome);
+                } else {
+                    try!(outcome);
+                    try!(world.go());
+                    watch.orders = None;
+                    watch.answered = true;
+                    answered.notify_all();
+                }
+            }
+            world.clear();
+        }
+        if let Some(ref result) = result {
+            self.on_game_end(&world, result);
+        }
+        Ok(())
+    }
+}
+
+/// Read the game from `input` and play it out, using `turn` to play each
+/// turn once its input has been read.
+///
+/// `turn` is responsible for sending the turn's orders and "go". If
+/// `resilient` is set, malformed input lines and failures in `do_setup` are
+/// recovered from rather than returned.
+fn play<B, R, W, F>(bot: &mut B, input: R, output: W, resilient: bool,
+                    mut turn: F) -> Result<()>
+        where B: Bot + ?Sized, R: BufRead, W: Write,
+              F: FnMut(&mut B, &mut World) -> Result<()> {
+    let number = Cell::new(0);
+    let mut lines = input.lines().inspect(|_| number.set(number.get() + 1));
+    let params = try!(Params::from_lines(&mut lines));
+    let mut world = World::new(&params, output);
+    let mut result = None;
+    if resilient {
+        let setup = panic::catch_unwind(AssertUnwindSafe(|| {
+            bot.do_setup(&mut world)
+        }));
+        if let Err(error) = flatten(setup) {
+            recover(&mut world, error);
+        }
+    } else {
+        try!(bot.do_setup(&mut world));
+    }
+    try!(world.go());
+    for line in lines {
+        let line = try!(line);
+        if line == "go" {
+            if let Some(ref result) = result {
+                bot.on_game_end(&world, result);
+                return Ok(());
+            }
+            world.begin_turn();
+            try!(turn(bot, &mut world));
+            world.clear();
+        } else {
+            let update = update(&mut world, &mut result, &*line);
+            match update.map_err(|e| e.at_line(number.get())) {
+                Err(error) if resilient => recover(&mut world, error),
+                update => try!(update),
             }
         }
+    }
+    if let Some(ref result) = result {
+        bot.on_game_end(&world, result);
+    }
+    Ok(())
+}
+
+/// Apply one input line other than "go" to the world or, once the game
+/// has ended, to its result.
+fn update(world: &mut World, result: &mut Option<GameResult>, line: &str)
+        -> Result<()> {
+    if line == "end" {
+        *result = Some(GameResult::new());
         Ok(())
+    } else if line.is_empty() {
+        Ok(())
+    } else {
+        match *result {
+            Some(ref mut result) if is_result_line(line) => {
+                result.update(line).map_err(|e| {
+                    Error::parse(world.turn(), line, e)
+                })
+            },
+            _ => world.update(line),
+        }
+    }
+}
+
+/// Log an error the bot is carrying on after, and record it on the world.
+fn recover(world: &mut World, error: Error) {
+    eprintln!("turn {}: recovered from: {}", world.turn(), error);
+    world.record_error(error);
+}
+
+/// Turn a panic caught while running the bot into an `Error::Panic`.
+fn flatten(result: thread::Result<Result<()>>) -> Result<()> {
+    match result {
+        Ok(result) => result,
+        Err(panic) => {
+            let message = match panic.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => match panic.downcast_ref::<String>() {
+                    Some(message) => message.clone(),
+                    None => "Bot panicked".to_string(),
+                },
+            };
+            Err(Error::Panic(message))
+        },
+    }
+}
+
+/// The turn being guarded by `Bot::run_guarded_with`.
+struct Watch {
+    /// How many turns have started, counting "go" lines.
+    turn: usize,
+    /// Whether "go" has been sent for the current turn.
+    answered: bool,
+    /// The orders queued so far, once `do_turn` has started on the turn.
+    orders: Option<Arc<Mutex<Vec<Order>>>>,
+    /// The world clock's safety margin.
+    margin: Duration,
+}
+
+/// Pass `lines` on to `sender`, with the time each arrived, and send the
+/// watched orders with "go" for any turn not answered by its deadline.
+fn watch<I, W>(lines: I, output: &Mutex<W>, state: &(Mutex<Watch>, Condvar),
+               turntime: Duration,
+               sender: Sender<(io::Result<String>, Instant)>)
+        where I: Iterator<Item = io::Result<String>>, W: Write {
+    let &(ref watch, ref answered) = state;
+    let mut ended = false;
+    for line in lines {
+        let arrived = Instant::now();
+        let go = match line {
+            Ok(ref line) => {
+                ended |= line == "end";
+                line == "go" && !ended
+            },
+            Err(_) => false,
+        };
+        if go {
+            let mut watch = watch.lock().unwrap();
+            watch.turn += 1;
+            watch.answered = false;
+            watch.orders = None;
+        }
+        if sender.send((line, arrived)).is_err() {
+            return;
+        }
+        if !go {
+            continue;
+        }
+        let mut watch = watch.lock().unwrap();
+        while !watch.answered {
+            let usable = turntime.checked_sub(watch.margin)
+                                 .unwrap_or(Duration::from_millis(0));
+            let now = Instant::now();
+            if now >= arrived + usable {
+                if let Err(error) = fallback(output, &watch.orders) {
+                    let _ = sender.send((Err(error), now));
+                    return;
+                }
+                watch.answered = true;
+            } else {
+                let timeout = arrived + usable - now;
+                watch = answered.wait_timeout(watch, timeout).unwrap().0;
+            }
+        }
+    }
+}
+
+/// Send `orders`, if any, and "go" in place of a late turn.
+fn fallback<W: Write>(output: &Mutex<W>,
+                      orders: &Option<Arc<Mutex<Vec<Order>>>>)
+        -> io::Result<()> {
+    let mut output = output.lock().unwrap();
+    if let Some(ref orders) = *orders {
+        for order in orders.lock().unwrap().iter() {
+            try!(writeln!(output, "{}", order));
+        }
+    }
+    try!(writeln!(output, "go"));
+    output.flush()
+}
+
+/// A writer shared between the main thread and a `World`.
+struct Shared<W>(Arc<Mutex<W>>);
+
+impl<W: Write> Write for Shared<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+/// Whether the line belongs to the `GameResult` rather than the `World`.
+fn is_result_line(line: &str) -> bool {
+    line.starts_with("players ") || line.starts_with("score ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use ants::direction::Direction;
+    use ants::point::Point;
+
+    struct NorthBot {
+        result: Option<GameResult>,
+    }
+
+    impl Bot for NorthBot {
+        fn do_turn(&mut self, world: &mut World) -> Result<()> {
+            try!(world.order(Point { row: 1, col: 1 }, Direction::East));
+            try!(world.order(Point { row: 2, col: 2 }, Direction::West));
+            try!(world.replace_order(Point { row: 1, col: 1 },
+                                     Direction::North));
+            world.cancel_order(Point { row: 2, col: 2 });
+            Ok(())
+        }
+
+        fn on_game_end(&mut self, world: &World, result: &GameResult) {
+            self.result = Some(result.clone());
+        }
+    }
+
+    #[test]
+    fn run_with() {
+        let input = "turn 0\nrows 4\ncols 4\nviewradius2 1\n\
+                     attackradius2 1\nspawnradius2 1\nready\n\
+                     turn 1\na 1 1 0\na 2 2 0\ngo\n\
+                     end\nplayers 2\nscore 1 0\ngo\n";
+        let mut output = Vec::new();
+        let mut bot = NorthBot { result: None };
+        bot.run_with(Cursor::new(input), &mut output).unwrap();
+        assert_eq!(&*String::from_utf8(output).unwrap(), "go\no 1 1 n\ngo\n");
+        assert_eq!(bot.result, Some(GameResult {
+            players: 2,
+            scores: vec![1, 0],
+        }));
+    }
+
+    struct SlowBot {
+        turns: usize,
+        overruns: usize,
+    }
+
+    impl Bot for SlowBot {
+        fn do_turn(&mut self, world: &mut World) -> Result<()> {
+            self.turns += 1;
+            try!(world.order(Point { row: 1, col: 1 }, Direction::North));
+            thread::sleep(Duration::from_millis(200));
+            try!(world.order(Point { row: 2, col: 2 }, Direction::North));
+            Ok(())
+        }
+
+        fn on_overrun(&mut self, world: &World, elapsed: Duration) {
+            self.overruns += 1;
+        }
+    }
+
+    #[test]
+    fn run_guarded_with() {
+        // the second turn's deadline passes while the first is still being
+        // played, so it is skipped
+        let input = "turn 0\nturntime 100\nrows 4\ncols 4\nviewradius2 1\n\
+                     attackradius2 1\nspawnradius2 1\nready\n\
+                     turn 1\na 1 1 0\na 2 2 0\ngo\n\
+                     turn 2\na 0 1 0\na 2 2 0\ngo\n";
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut bot = SlowBot { turns: 0, overruns: 0 };
+        bot.run_guarded_with(Cursor::new(input), Shared(output.clone()))
+           .unwrap();
+        let output = output.lock().unwrap().clone();
+        assert_eq!(&*String::from_utf8(output).unwrap(),
+                   "go\no 1 1 n\ngo\ngo\n");
+        assert_eq!(bot.turns, 1);
+        assert_eq!(bot.overruns, 1);
+    }
+
+    struct PanicBot {
+        recovered: Option<usize>,
+    }
+
+    impl Bot for PanicBot {
+        fn do_turn(&mut self, world: &mut World) -> Result<()> {
+            try!(world.order(Point { row: 1, col: 1 }, Direction::North));
+            panic!("lost");
+        }
+
+        fn on_game_end(&mut self, world: &World, result: &GameResult) {
+            self.recovered = Some(world.recovered_errors().len());
+        }
+    }
+
+    #[test]
+    fn run_resilient_with() {
+        let input = "turn 0\nrows 4\ncols 4\nviewradius2 1\n\
+                     attackradius2 1\nspawnradius2 1\nready\n\
+                     turn 1\nx 1 1\na 1 1 0\ngo\n\
+                     end\nplayers 2\nscore 1 0\ngo\n";
+        let mut output = Vec::new();
+        let mut bot = PanicBot { recovered: None };
+        bot.run_resilient_with(Cursor::new(input), &mut output).unwrap();
+        assert_eq!(&*String::from_utf8(output).unwrap(), "go\no 1 1 n\ngo\n");
+        // the bad line and the panic
+        assert_eq!(bot.recovered, Some(2));
     }
 }
@@ -0,0 +1,91 @@
+//! Time keeping against the engine's deadlines.
+
+use std::time::{Duration, Instant};
+
+/// Time held back from the budget by default, in milliseconds.
+///
+/// Covers writing orders and the engine's own latency.
+const DEFAULT_MARGIN_MS: u64 = 50;
+
+/// Tracks the time spent in one phase of the game against its budget.
+///
+/// The budget is `loadtime` during setup and `turntime` during each turn.
+/// A safety margin is kept back from the budget so that the bot has time to
+/// send its orders before the engine's deadline.
+#[derive(Debug, Copy, Clone)]
+pub struct Clock {
+    start: Instant,
+    budget: Duration,
+    margin: Duration,
+}
+
+impl Clock {
+    /// Start a new clock now, with the given budget and the default margin.
+    pub fn new(budget: Duration) -> Clock {
+        Clock {
+            start: Instant::now(),
+            budget: budget,
+            margin: Duration::from_millis(DEFAULT_MARGIN_MS),
+        }
+    }
+
+    /// Start the clock again now with a new budget, keeping the margin.
+    pub fn restart(&mut self, budget: Duration) {
+        self.restart_at(Instant::now(), budget);
+    }
+
+    /// Start the clock again from `start` with a new budget, keeping the
+    /// margin.
+    pub fn restart_at(&mut self, start: Instant, budget: Duration) {
+        self.start = start;
+        self.budget = budget;
+    }
+
+    /// The instant the current phase began.
+    pub fn start(&self) -> Instant {
+        self.start
+    }
+
+    /// Total time allowed by the engine for the current phase.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Time held back from the budget.
+    pub fn margin(&self) -> Duration {
+        self.margin
+    }
+
+    /// Set the time held back from the budget.
+    pub fn set_margin(&mut self, margin: Duration) {
+        self.margin = margin;
+    }
+
+    /// The instant by which the bot should be done, margin included.
+    pub fn deadline(&self) -> Instant {
+        let usable = self.budget.checked_sub(self.margin)
+                                .unwrap_or(Duration::from_millis(0));
+        self.start + usable
+    }
+
+    /// Time since the current phase began.
+    pub fn time_elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Time left before the deadline, margin included; zero once it passed.
+    pub fn time_remaining(&self) -> Duration {
+        let now = Instant::now();
+        let deadline = self.deadline();
+        if now < deadline {
+            deadline - now
+        } else {
+            Duration::from_millis(0)
+        }
+    }
+
+    /// Whether the deadline, margin included, has passed.
+    pub fn is_out_of_time(&self) -> bool {
+        Instant::now() >= self.deadline()
+    }
+}
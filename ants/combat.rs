@@ -0,0 +1,124 @@
+//! Battle resolution under the game's "focus" rule.
+//!
+//! Every ant splits its attack between all enemies within the attack
+//! radius. An ant dies if it is attacked by at least as many enemies as the
+//! least-attacked enemy it is fighting. Which ants die depends only on the
+//! positions and owners of the ants after moving, so bots can use this to
+//! predict the outcome of a move, and a local engine to play one out.
+
+use ants::params::Params;
+use ants::player::Player;
+use ants::point::Point;
+
+/// Work out which of `ants` die in battle.
+///
+/// Each ant is given by its position and owner. Distances use
+/// `params.attackradius2` and wrap around a map of `params.rows` by
+/// `params.cols`. The result is aligned with `ants`: `true` where the ant
+/// dies.
+pub fn resolve(ants: &[(Point, Player)], params: &Params) -> Vec<bool> {
+    let enemies = enemies_in_range(ants, params);
+    enemies.iter().map(|enemies_of_ant| {
+        let weakness = enemies_of_ant.len();
+        enemies_of_ant.iter().any(|&enemy| weakness >= enemies[enemy].len())
+    }).collect()
+}
+
+/// The points of the ants which die in battle.
+///
+/// As `resolve`, but only the positions of the dead are returned.
+pub fn casualties(ants: &[(Point, Player)], params: &Params) -> Vec<Point> {
+    resolve(ants, params).iter().zip(ants.iter())
+        .filter(|&(&dies, _)| dies)
+        .map(|(_, &(point, _))| point)
+        .collect()
+}
+
+/// For each ant, the indices of the enemy ants within attack range.
+pub fn enemies_in_range(ants: &[(Point, Player)], params: &Params)
+        -> Vec<Vec<usize>> {
+    let (rows, cols) = (params.rows, params.cols);
+    let mut enemies = vec![Vec::new(); ants.len()];
+    for (i, &(a, a_owner)) in ants.iter().enumerate() {
+        for (j, &(b, b_owner)) in ants.iter().enumerate().skip(i + 1) {
+            if a_owner != b_owner &&
+                    a.distance2(b, rows, cols) <= params.attackradius2 {
+                enemies[i].push(j);
+                enemies[j].push(i);
+            }
+        }
+    }
+    enemies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Params {
+        let mut params = Params::new();
+        params.rows = 20;
+        params.cols = 20;
+        params.attackradius2 = 5;
+        params
+    }
+
+    fn ant(row: i32, col: i32, owner: u8) -> (Point, Player) {
+        let owner = if owner == 0 { Player::Me } else { Player::Other(owner) };
+        (Point { row: row, col: col }, owner)
+    }
+
+    #[test]
+    fn one_on_one() {
+        let ants = [ant(5, 5, 0), ant(5, 7, 1)];
+        assert_eq!(resolve(&ants, &params()), vec![true, true]);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let ants = [ant(5, 5, 0), ant(5, 8, 1), ant(4, 5, 0)];
+        assert_eq!(resolve(&ants, &params()), vec![false, false, false]);
+    }
+
+    #[test]
+    fn outnumbered() {
+        // two of ours against one of theirs: only theirs dies
+        let ants = [ant(5, 5, 0), ant(7, 5, 0), ant(6, 6, 1)];
+        assert_eq!(resolve(&ants, &params()), vec![false, false, true]);
+        assert_eq!(casualties(&ants, &params()),
+                   vec![Point { row: 6, col: 6 }]);
+    }
+
+    #[test]
+    fn focus() {
+        // A line of three of ours against a line of three of theirs, each
+        // ant only in range of the enemies directly opposite and diagonal:
+        // the middle ants face three enemies, the ends two.
+        let ants = [
+            ant(5, 4, 0), ant(5, 6, 0), ant(5, 8, 0),
+            ant(7, 4, 1), ant(7, 6, 1), ant(7, 8, 1),
+        ];
+        let mut params = params();
+        params.attackradius2 = 8;
+        assert_eq!(resolve(&ants, &params),
+                   vec![true, true, true, true, true, true]);
+        // take away one of theirs at the end, and our far end survives
+        let ants = &ants[..5];
+        assert_eq!(resolve(ants, &params),
+                   vec![true, true, false, true, true]);
+    }
+
+    #[test]
+    fn three_players() {
+        // ours is caught between two players who are not in range of each
+        // other
+        let ants = [ant(5, 5, 0), ant(5, 3, 1), ant(5, 7, 2)];
+        assert_eq!(resolve(&ants, &params()), vec![true, false, false]);
+    }
+
+    #[test]
+    fn wraps() {
+        let ants = [ant(0, 0, 0), ant(19, 19, 1)];
+        assert_eq!(resolve(&ants, &params()), vec![true, true]);
+    }
+}
@@ -0,0 +1,242 @@
+//! Collaborative diffusion ("scent") maps.
+//!
+//! Things worth going to, such as food, unexplored land and enemy hills,
+//! emit scent which spreads across passable tiles, fading with distance.
+//! Each ant then simply climbs the combined gradient. When our own ants
+//! block a layer's scent, an ant which is already heading for a source
+//! hides it from the ants behind, so they spread out without any explicit
+//! assignment.
+
+use ants::direction::Direction;
+use ants::grid::Grid;
+use ants::map::{Map, UnknownPolicy};
+use ants::point::Point;
+
+/// One kind of scent, with its own sources and spreading rules.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    /// How much this layer counts towards the combined scent. Negative
+    /// weights repel.
+    pub weight: f32,
+    /// Fraction of the scent kept on each step it spreads, between 0 and 1.
+    pub decay: f32,
+    /// Whether our own ants stop this scent from spreading past them.
+    pub blocked_by_ants: bool,
+    sources: Vec<(Point, f32)>,
+    values: Grid<f32>,
+}
+
+impl Layer {
+    /// Create a layer with no sources for a map with the given dimensions.
+    fn new(rows: i32, cols: i32, weight: f32, decay: f32) -> Layer {
+        Layer {
+            weight: weight,
+            decay: decay,
+            blocked_by_ants: false,
+            sources: Vec::new(),
+            values: Grid::new(rows, cols, 0.0),
+        }
+    }
+
+    /// Add a source emitting scent of the given `strength` at `point`.
+    pub fn emit(&mut self, point: Point, strength: f32) {
+        self.sources.push((point, strength));
+    }
+
+    /// Remove all sources, e.g. at the start of a turn.
+    ///
+    /// The spread scent is kept, so that the next `Diffusion::diffuse`
+    /// starts from last turn's values and settles in fewer iterations.
+    pub fn clear_sources(&mut self) {
+        self.sources.clear();
+    }
+
+    /// The scent of this layer alone at `point`.
+    pub fn value(&self, point: Point) -> f32 {
+        self.values[point]
+    }
+}
+
+/// A set of weighted scent layers spread over the map together.
+#[derive(Debug, Clone)]
+pub struct Diffusion {
+    rows: i32,
+    cols: i32,
+    layers: Vec<Layer>,
+    iterations: usize,
+    unknown: UnknownPolicy,
+    ants: Grid<bool>,
+}
+
+impl Diffusion {
+    /// Create a diffusion with no layers for a map with the given
+    /// dimensions, spreading for `iterations` steps.
+    ///
+    /// Scent travels at most one tile per iteration, so this bounds how far
+    /// it reaches. Unseen tiles are treated according to `unknown`, both
+    /// when spreading scent and when following it; with
+    /// `UnknownPolicy::Passable`, scent emitted on the frontier of explored
+    /// land spreads into the unseen tiles beyond it.
+    pub fn new(rows: i32, cols: i32, iterations: usize,
+               unknown: UnknownPolicy) -> Diffusion {
+        Diffusion {
+            rows: rows,
+            cols: cols,
+            layers: Vec::new(),
+            iterations: iterations,
+            unknown: unknown,
+            ants: Grid::new(rows, cols, false),
+        }
+    }
+
+    /// Add a layer with no sources and the given weight and decay,
+    /// returning its index.
+    pub fn add_layer(&mut self, weight: f32, decay: f32) -> usize {
+        self.layers.push(Layer::new(self.rows, self.cols, weight, decay));
+        self.layers.len() - 1
+    }
+
+    /// The layer with the given index.
+    pub fn layer(&self, index: usize) -> &Layer {
+        &self.layers[index]
+    }
+
+    /// The layer with the given index, e.g. to add sources.
+    pub fn layer_mut(&mut self, index: usize) -> &mut Layer {
+        &mut self.layers[index]
+    }
+
+    /// Set how many steps the scent spreads for on each `diffuse`.
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    /// Set where our own ants stand, for layers they block.
+    pub fn set_ants(&mut self, ants: &[Point]) {
+        self.ants.fill(false);
+        for &ant in ants {
+            self.ants[ant] = true;
+        }
+    }
+
+    /// Spread every layer's scent from its sources across `map`.
+    ///
+    /// On each iteration, a tile takes the layer's decay times the mean
+    /// scent of its four neighbours, while sources keep their strength.
+    /// Water, and unseen tiles unless they are treated as passable, never
+    /// hold scent.
+    pub fn diffuse(&mut self, map: &Map) {
+        let (rows, cols) = (map.rows(), map.cols());
+        let ants = &self.ants;
+        let unknown = self.unknown;
+        for layer in self.layers.iter_mut() {
+            let mut next = layer.values.clone();
+            for _ in 0..self.iterations {
+                for (point, _) in map.tiles() {
+                    let blocked = !map.is_walkable(point, unknown) ||
+                        (layer.blocked_by_ants && ants[point]);
+                    next[point] = if blocked {
+                        0.0
+                    } else {
+                        let sum: f32 = point.neighbors(rows, cols).iter()
+                            .map(|&n| layer.values[n]).sum();
+                        layer.decay * sum / 4.0
+                    };
+                }
+                for &(source, strength) in layer.sources.iter() {
+                    next[source] = strength;
+                }
+                ::std::mem::swap(&mut layer.values, &mut next);
+            }
+        }
+    }
+
+    /// The combined, weighted scent of all layers at `point`.
+    pub fn scent(&self, point: Point) -> f32 {
+        self.layers.iter().map(|l| l.weight * l.values[point]).sum()
+    }
+
+    /// The move from `from` towards the strongest combined scent.
+    ///
+    /// Every move onto a walkable tile is compared, including those onto
+    /// tiles with no scent, so that repelling layers push ants away.
+    /// Returns `None` if there are no such moves, if they all smell the
+    /// same, or if none smells better than staying at `from`.
+    pub fn best_direction(&self, map: &Map, from: Point) -> Option<Direction> {
+        let (rows, cols) = (map.rows(), map.cols());
+        let scents: Vec<(Direction, f32)> = Direction::ALL.iter()
+            .map(|&direction| (direction, (from + direction).wrap(rows, cols)))
+            .filter(|&(_, to)| map.is_walkable(to, self.unknown))
+            .map(|(direction, to)| (direction, self.scent(to)))
+            .collect();
+        let mut best: Option<(Direction, f32)> = None;
+        for &(direction, scent) in scents.iter() {
+            match best {
+                Some((_, best_scent)) if best_scent >= scent => {},
+                _ => best = Some((direction, scent)),
+            }
+        }
+        let here = self.scent(from.wrap(rows, cols));
+        best.and_then(|(direction, scent)| {
+            let same = scents.iter().all(|&(_, other)| other == scent);
+            if same || scent <= here { None } else { Some(direction) }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::tile::Tile;
+
+    #[test]
+    fn climbs_gradient() {
+        let map = Map::filled(1, 9, Tile::land());
+        let mut diffusion = Diffusion::new(1, 9, 10, UnknownPolicy::Blocked);
+        let food = diffusion.add_layer(1.0, 0.9);
+        diffusion.layer_mut(food).emit(Point { row: 0, col: 6 }, 1.0);
+        diffusion.diffuse(&map);
+        let ant = Point { row: 0, col: 3 };
+        assert_eq!(diffusion.best_direction(&map, ant), Some(Direction::East));
+
+        // another ant nearer the food hides it from the first
+        diffusion.layer_mut(food).blocked_by_ants = true;
+        diffusion.set_ants(&[Point { row: 0, col: 5 }]);
+        diffusion.diffuse(&map);
+        assert_eq!(diffusion.best_direction(&map, ant), Some(Direction::West));
+    }
+
+    #[test]
+    fn repelled() {
+        // the scent only reaches two tiles from the source, so the tile
+        // west of the ant smells of nothing, which beats staying close
+        let map = Map::filled(1, 9, Tile::land());
+        let mut diffusion = Diffusion::new(1, 9, 3, UnknownPolicy::Blocked);
+        let danger = diffusion.add_layer(-1.0, 0.9);
+        diffusion.layer_mut(danger).emit(Point { row: 0, col: 6 }, 1.0);
+        diffusion.diffuse(&map);
+        let ant = Point { row: 0, col: 4 };
+        assert_eq!(diffusion.best_direction(&map, ant), Some(Direction::West));
+    }
+
+    #[test]
+    fn unseen_tiles() {
+        // only the source's own tile has been seen
+        let mut map = Map::new(1, 9);
+        let source = Point { row: 0, col: 6 };
+        map[source] = Tile::land();
+        let ant = Point { row: 0, col: 3 };
+        let mut diffusion = Diffusion::new(1, 9, 10, UnknownPolicy::Passable);
+        let explore = diffusion.add_layer(1.0, 0.9);
+        diffusion.layer_mut(explore).emit(source, 1.0);
+        diffusion.diffuse(&map);
+        assert_eq!(diffusion.best_direction(&map, ant), Some(Direction::East));
+
+        let mut diffusion = Diffusion::new(1, 9, 10, UnknownPolicy::Blocked);
+        let explore = diffusion.add_layer(1.0, 0.9);
+        diffusion.layer_mut(explore).emit(source, 1.0);
+        diffusion.diffuse(&map);
+        assert_eq!(diffusion.layer(explore).value(ant), 0.0);
+        assert_eq!(diffusion.best_direction(&map, ant), None);
+    }
+}
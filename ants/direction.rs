@@ -1,10 +1,12 @@
 //! Cardinal directions.
 
 use std::fmt;
+use std::str::FromStr;
+use ants::error::{Result, Error};
 use ants::point::Point;
 
 /// Represents the four cardinal directions in the game.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Direction {
     North,
     South,
@@ -13,6 +15,53 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// All four directions, in the order north, south, east, west.
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    /// The direction which undoes a move in this one.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    /// The direction a quarter turn counter-clockwise from this one.
+    pub fn left(self) -> Direction {
+        match self {
+            Direction::North => Direction::West,
+            Direction::South => Direction::East,
+            Direction::East => Direction::North,
+            Direction::West => Direction::South,
+        }
+    }
+
+    /// The direction a quarter turn clockwise from this one.
+    pub fn right(self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::South => Direction::West,
+            Direction::East => Direction::South,
+            Direction::West => Direction::North,
+        }
+    }
+
+    /// The direction of a single move from `a` to `b` on the torus.
+    ///
+    /// Returns `None` unless the points are adjacent.
+    pub fn between(a: Point, b: Point, rows: i32, cols: i32)
+            -> Option<Direction> {
+        let delta = a.delta(b, rows, cols);
+        Direction::ALL.iter().cloned().find(|d| d.into_point() == delta)
+    }
+
     /// Convert the direction into a 'unit point'.
     ///
     /// The result can be added to a `Point` to offset it one unit in any
@@ -27,8 +76,24 @@ impl Direction {
     }
 }
 
+impl FromStr for Direction {
+    type Err = Error;
+
+    /// Parse a direction from its protocol letter, one of "n", "s", "e" or
+    /// "w".
+    fn from_str(s: &str) -> Result<Direction> {
+        match s {
+            "n" => Ok(Direction::North),
+            "s" => Ok(Direction::South),
+            "e" => Ok(Direction::East),
+            "w" => Ok(Direction::West),
+            _ => Err(Error::BadDirection),
+        }
+    }
+}
+
 impl fmt::Display for Direction {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", match *self {
             Direction::North => "n",
             Direction::South => "s",
@@ -37,3 +102,34 @@ impl fmt::Display for Direction {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation() {
+        for &d in Direction::ALL.iter() {
+            assert_eq!(d.opposite().opposite(), d);
+            assert_eq!(d.left().right(), d);
+            assert_eq!(d.right().right(), d.opposite());
+        }
+    }
+
+    #[test]
+    fn parse() {
+        for &d in Direction::ALL.iter() {
+            assert_eq!(d.to_string().parse::<Direction>().unwrap(), d);
+        }
+        assert!("x".parse::<Direction>().is_err());
+    }
+
+    #[test]
+    fn between() {
+        let a = Point { row: 0, col: 0 };
+        let b = Point { row: 4, col: 0 };
+        assert_eq!(Direction::between(a, b, 5, 5), Some(Direction::North));
+        assert_eq!(Direction::between(a, a, 5, 5), None);
+        assert_eq!(Direction::between(a, Point { row: 1, col: 1 }, 5, 5), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! Breadth-first distance fields over the map.
+
+use std::collections::VecDeque;
+use ants::direction::Direction;
+use ants::grid::Grid;
+use ants::map::{Map, UnknownPolicy};
+use ants::point::Point;
+
+/// Walking distance from every tile to the nearest of a set of sources.
+///
+/// Computed with a multi-source breadth-first search which wraps around
+/// the map and never enters water. This is the basis of most movement
+/// decisions: distance to the nearest food, the nearest enemy hill, and so
+/// on.
+#[derive(Debug, Clone)]
+pub struct DistanceField {
+    distances: Grid<Option<i32>>,
+    sources: Grid<Option<Point>>,
+}
+
+impl DistanceField {
+    /// Search outwards from all of `sources` at once.
+    ///
+    /// Tiles further than `max_depth` moves from every source, if given,
+    /// are left unreached. Unseen tiles are treated according to `unknown`.
+    pub fn new(map: &Map, sources: &[Point], max_depth: Option<i32>,
+               unknown: UnknownPolicy) -> DistanceField {
+        let (rows, cols) = (map.rows(), map.cols());
+        let mut field = DistanceField {
+            distances: Grid::new(rows, cols, None),
+            sources: Grid::new(rows, cols, None),
+        };
+        let mut queue = VecDeque::new();
+        for &source in sources {
+            let source = source.wrap(rows, cols);
+            if field.distances[source].is_none() {
+                field.distances[source] = Some(0);
+                field.sources[source] = Some(source);
+                queue.push_back(source);
+            }
+        }
+        while let Some(point) = queue.pop_front() {
+            let distance = field.distances[point].unwrap();
+            if max_depth.map_or(false, |max| distance >= max) {
+                continue;
+            }
+            for &next in point.neighbors(rows, cols).iter() {
+                if field.distances[next].is_none() &&
+                        map.is_walkable(next, unknown) {
+                    field.distances[next] = Some(distance + 1);
+                    field.sources[next] = field.sources[point];
+                    queue.push_back(next);
+                }
+            }
+        }
+        field
+    }
+
+    /// Number of moves from `point` to the nearest source, if reachable.
+    pub fn distance(&self, point: Point) -> Option<i32> {
+        self.distances[point]
+    }
+
+    /// The source nearest to `point`, if any is reachable.
+    ///
+    /// Ties between equally near sources are broken arbitrarily.
+    pub fn source(&self, point: Point) -> Option<Point> {
+        self.sources[point]
+    }
+
+    /// The first move from `point` along a shortest path to the nearest
+    /// source.
+    ///
+    /// Returns `None` if `point` is a source or no source is reachable.
+    pub fn direction(&self, point: Point) -> Option<Direction> {
+        let distance = match self.distances[point] {
+            Some(0) | None => return None,
+            Some(distance) => distance,
+        };
+        Direction::ALL.iter().cloned().find(|&d| {
+            self.distances[point + d] == Some(distance - 1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::tile::Tile;
+
+    #[test]
+    fn around_water() {
+        // . W .
+        // . W .
+        // . . .
+        // . . .
+        let mut map = Map::filled(4, 3, Tile::land());
+        map[Point { row: 0, col: 1 }] = Tile::water();
+        map[Point { row: 1, col: 1 }] = Tile::water();
+        let source = Point { row: 0, col: 0 };
+        let field = DistanceField::new(&map, &[source], None,
+                                       UnknownPolicy::Blocked);
+        let goal = Point { row: 0, col: 2 };
+        assert_eq!(field.distance(goal), Some(1));
+        assert_eq!(field.direction(goal), Some(Direction::East));
+        assert_eq!(field.distance(Point { row: 1, col: 1 }), None);
+        assert_eq!(field.source(goal), Some(source));
+
+        let field = DistanceField::new(&map, &[source], Some(1),
+                                       UnknownPolicy::Blocked);
+        assert_eq!(field.distance(Point { row: 2, col: 0 }), None);
+    }
+
+    #[test]
+    fn unknown_policy() {
+        let map = Map::new(3, 3);
+        let source = Point { row: 0, col: 0 };
+        let open = DistanceField::new(&map, &[source], None,
+                                      UnknownPolicy::Passable);
+        let blocked = DistanceField::new(&map, &[source], None,
+                                         UnknownPolicy::Blocked);
+        assert_eq!(open.distance(Point { row: 1, col: 1 }), Some(2));
+        assert_eq!(blocked.distance(Point { row: 1, col: 1 }), None);
+    }
+}
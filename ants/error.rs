@@ -3,6 +3,8 @@
 use std::num::ParseIntError;
 use std::fmt::{self, Display, Formatter};
 use std::io;
+use std::error::Error as StdError;
+use ants::order::OrderError;
 
 /// Convenience alias for `Result` using our `Error` type.
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -10,8 +12,10 @@ pub type Result<T> = ::std::result::Result<T, Error>;
 /// Any error producible by the library.
 #[derive(Debug)]
 pub enum Error {
-    /// An unknown or malformed initial world parameter was encountered.
+    /// A malformed initial world parameter was encountered.
     BadParameter,
+    /// The named world parameter has a value which makes no sense.
+    InvalidParameter(&'static str),
     /// An attempt to parse an input as an integer failed.
     ParseInt(ParseIntError),
     UnexpectedLine,
@@ -21,24 +25,90 @@ pub enum Error {
     Io(io::Error),
     /// An unknown or malformed turn input command was encountered.
     UnknownCommand,
+    /// A direction was not one of the protocol letters "n", "s", "e", "w".
+    BadDirection,
+    /// An order was refused as invalid.
+    Order(OrderError),
+    /// The bot panicked, with the given message.
+    Panic(String),
+    /// A line of input could not be parsed.
+    Parse {
+        /// The turn during which the line was read.
+        turn: i32,
+        /// The line's number in the input, counting from 1, if known.
+        line: Option<usize>,
+        /// The offending line.
+        text: String,
+        /// What was wrong with the line.
+        cause: Box<Error>,
+    },
 }
 
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
+impl Error {
+    /// Wrap `cause` with the line of input which produced it.
+    ///
+    /// The line number is usually only known to the caller reading the
+    /// input; it can be added later with `at_line`.
+    pub fn parse(turn: i32, text: &str, cause: Error) -> Error {
+        Error::Parse {
+            turn: turn,
+            line: None,
+            text: text.to_string(),
+            cause: Box::new(cause),
+        }
+    }
+
+    /// Add the line number to a parse error which is missing one.
+    ///
+    /// Other errors are returned unchanged.
+    pub fn at_line(self, number: usize) -> Error {
+        match self {
+            Error::Parse { turn, line: None, text, cause } => Error::Parse {
+                turn: turn,
+                line: Some(number),
+                text: text,
+                cause: cause,
+            },
+            error => error,
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match *self {
-            Error::BadParameter => "Bad world parameter",
-            Error::ParseInt(ref e) => ::std::error::Error::description(e),
-            Error::UnexpectedLine => "Unexpected line",
-            Error::UnexpectedEof => "Unexpected Eof",
-            Error::Io(ref e) => e.description(),
-            Error::UnknownCommand => "Unknown command",
+            Error::ParseInt(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            Error::Order(ref e) => Some(e),
+            Error::Parse { ref cause, .. } => Some(&**cause),
+            _ => None,
         }
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> ::std::result::Result<(), fmt::Error> {
-        write!(f, "{}", ::std::error::Error::description(self))
+        match *self {
+            Error::BadParameter => write!(f, "Bad world parameter"),
+            Error::InvalidParameter(name) => {
+                write!(f, "Invalid world parameter `{}`", name)
+            },
+            Error::ParseInt(ref e) => write!(f, "{}", e),
+            Error::UnexpectedLine => write!(f, "Unexpected line"),
+            Error::UnexpectedEof => write!(f, "Unexpected Eof"),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::UnknownCommand => write!(f, "Unknown command"),
+            Error::BadDirection => write!(f, "Bad direction"),
+            Error::Order(ref e) => write!(f, "{}", e),
+            Error::Panic(ref message) => write!(f, "{}", message),
+            Error::Parse { turn, line, ref text, ref cause } => {
+                try!(write!(f, "turn {}", turn));
+                if let Some(line) = line {
+                    try!(write!(f, ", line {}", line));
+                }
+                write!(f, ": {} in `{}`", cause, text)
+            },
+        }
     }
 }
 
@@ -53,3 +123,9 @@ impl ::std::convert::From<io::Error> for Error {
         Error::Io(err)
     }
 }
+
+impl ::std::convert::From<OrderError> for Error {
+    fn from(err: OrderError) -> Error {
+        Error::Order(err)
+    }
+}
@@ -0,0 +1,258 @@
+//! Assigning our ants to the food they should collect.
+//!
+//! Sending the nearest ant to each food in turn lets several ants chase the
+//! same food while other food is left alone. Instead we match ants to food
+//! so that the total walking distance is as small as possible, falling back
+//! to a greedy match when there are too many of either to solve exactly in
+//! good time.
+
+use ants::distance::DistanceField;
+use ants::map::{Map, UnknownPolicy};
+use ants::params::Params;
+use ants::point::Point;
+
+/// Above this many ants or food, the greedy match is used.
+const OPTIMAL_LIMIT: usize = 150;
+
+/// Cost standing in for an impossible pairing in the optimal match.
+const UNREACHABLE: i64 = 1 << 40;
+
+/// One ant sent to collect one food.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub ant: Point,
+    pub food: Point,
+    /// Number of moves from the ant to the food; 0 if the ant is already
+    /// close enough to collect it.
+    pub distance: i32,
+}
+
+/// Match `ants` to `food`, at most one each, for the least total distance.
+///
+/// Food within `params.spawnradius2` of one of our ants is collected by the
+/// first such ant without moving, and neither is matched with anything
+/// else. The rest are matched by walking distance, with unseen tiles
+/// treated according to `unknown`. Ants further than `max_distance` moves
+/// from a food, if given, are never sent to it.
+///
+/// Assignments are returned in the order the food is given.
+pub fn assign(map: &Map, params: &Params, ants: &[Point], food: &[Point],
+              max_distance: Option<i32>, unknown: UnknownPolicy)
+        -> Vec<Assignment> {
+    let (rows, cols) = (map.rows(), map.cols());
+    let mut collected = vec![None; food.len()];
+    let mut busy = vec![false; ants.len()];
+    for (f, &food) in food.iter().enumerate() {
+        let collector = ants.iter().position(|&ant| {
+            ant.distance2(food, rows, cols) <= params.spawnradius2
+        });
+        if let Some(a) = collector {
+            collected[f] = Some(a);
+            busy[a] = true;
+        }
+    }
+
+    // walking distances from each remaining food to each free ant
+    let free_food: Vec<usize> = (0..food.len())
+        .filter(|&f| collected[f].is_none()).collect();
+    let free_ants: Vec<usize> = (0..ants.len())
+        .filter(|&a| !busy[a]).collect();
+    let costs: Vec<Vec<Option<i32>>> = free_food.iter().map(|&f| {
+        let field = DistanceField::new(map, &[food[f]], max_distance,
+                                       unknown);
+        free_ants.iter().map(|&a| field.distance(ants[a])).collect()
+    }).collect();
+    let matched = if free_food.len().max(free_ants.len()) <= OPTIMAL_LIMIT {
+        optimal(&costs)
+    } else {
+        greedy(&costs)
+    };
+
+    let mut targets: Vec<Option<(usize, i32)>> = collected.iter()
+        .map(|collector| collector.map(|a| (a, 0)))
+        .collect();
+    for (k, &f) in free_food.iter().enumerate() {
+        if let Some(i) = matched[k] {
+            targets[f] = Some((free_ants[i], costs[k][i].unwrap()));
+        }
+    }
+    food.iter().zip(targets.iter())
+        .filter_map(|(&food, &target)| target.map(|(a, distance)| {
+            Assignment { ant: ants[a], food: food, distance: distance }
+        }))
+        .collect()
+}
+
+/// For each row of `costs`, the column it is matched with, if any, for the
+/// least total cost, using the Hungarian algorithm.
+///
+/// `None` costs are never matched.
+fn optimal(costs: &[Vec<Option<i32>>]) -> Vec<Option<usize>> {
+    let rows = costs.len();
+    let cols = costs.first().map_or(0, |row| row.len());
+    if rows == 0 || cols == 0 {
+        return vec![None; rows];
+    }
+    // the algorithm needs no more rows than cols
+    let transpose = rows > cols;
+    let (n, m) = if transpose { (cols, rows) } else { (rows, cols) };
+    let cost = |i: usize, j: usize| -> i64 {
+        let c = if transpose { costs[j][i] } else { costs[i][j] };
+        c.map_or(UNREACHABLE, |c| c as i64)
+    };
+
+    // potentials and matching are 1-based, with 0 as a free sentinel
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; m + 1];
+    let mut p = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+    for i in 1..n + 1 {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![i64::max_value(); m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = i64::max_value();
+            let mut j1 = 0;
+            for j in 1..m + 1 {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..m + 1 {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+        }
+    }
+
+    let mut matched = vec![None; rows];
+    for j in 1..m + 1 {
+        if p[j] == 0 || cost(p[j] - 1, j - 1) == UNREACHABLE {
+            continue;
+        }
+        let (i, j) = (p[j] - 1, j - 1);
+        if transpose {
+            matched[j] = Some(i);
+        } else {
+            matched[i] = Some(j);
+        }
+    }
+    matched
+}
+
+/// As `optimal`, but repeatedly matching the cheapest remaining pair.
+///
+/// Much faster on large inputs, though the total cost may be higher.
+fn greedy(costs: &[Vec<Option<i32>>]) -> Vec<Option<usize>> {
+    let mut pairs = Vec::new();
+    for (i, row) in costs.iter().enumerate() {
+        for (j, &cost) in row.iter().enumerate() {
+            if let Some(cost) = cost {
+                pairs.push((cost, i, j));
+            }
+        }
+    }
+    pairs.sort();
+    let cols = costs.first().map_or(0, |row| row.len());
+    let mut matched = vec![None; costs.len()];
+    let mut taken = vec![false; cols];
+    for (_, i, j) in pairs {
+        if matched[i].is_none() && !taken[j] {
+            matched[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::tile::Tile;
+
+    fn setup() -> (Map, Params) {
+        let mut params = Params::new();
+        params.rows = 1;
+        params.cols = 20;
+        params.spawnradius2 = 1;
+        let map = Map::filled(1, 20, Tile::land());
+        (map, params)
+    }
+
+    fn point(col: i32) -> Point {
+        Point { row: 0, col: col }
+    }
+
+    fn assignment(ant: i32, food: i32, distance: i32) -> Assignment {
+        Assignment { ant: point(ant), food: point(food), distance: distance }
+    }
+
+    #[test]
+    fn least_total_distance() {
+        // greedy would send the ant at 5 to the food at 3, leaving the ant
+        // at 0 a long walk to 7
+        let (map, params) = setup();
+        let ants = [point(0), point(5)];
+        let food = [point(3), point(7)];
+        let unknown = UnknownPolicy::Passable;
+        assert_eq!(assign(&map, &params, &ants, &food, None, unknown), vec![
+            assignment(0, 3, 3),
+            assignment(5, 7, 2),
+        ]);
+        let costs = vec![vec![Some(3), Some(1)], vec![Some(7), Some(3)]];
+        assert_eq!(optimal(&costs), vec![Some(0), Some(1)]);
+        assert_eq!(greedy(&costs), vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn spare_ants_and_food() {
+        let costs = vec![vec![Some(2), Some(5), Some(1)]];
+        assert_eq!(optimal(&costs), vec![Some(2)]);
+        let costs = vec![vec![Some(4)], vec![None], vec![Some(1)]];
+        assert_eq!(optimal(&costs), vec![None, None, Some(0)]);
+        let costs = vec![vec![None, Some(9)], vec![None, Some(2)]];
+        assert_eq!(optimal(&costs), vec![None, Some(1)]);
+    }
+
+    #[test]
+    fn spawn_radius() {
+        // the ant at 5 collects the food at 6 where it stands, so the ant
+        // at 8 goes on to the food at 12
+        let (map, params) = setup();
+        let ants = [point(5), point(8)];
+        let food = [point(6), point(12)];
+        let unknown = UnknownPolicy::Passable;
+        assert_eq!(assign(&map, &params, &ants, &food, None, unknown), vec![
+            assignment(5, 6, 0),
+            assignment(8, 12, 4),
+        ]);
+        // too far to walk
+        assert_eq!(assign(&map, &params, &ants, &food, Some(3), unknown),
+                   vec![assignment(5, 6, 0)]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Final outcome of a game.
+
+use ants::error::{Result, Error};
+use ants::player::Player;
+
+/// Results reported by the engine once the game has ended.
+///
+/// Scores are indexed by the engine's player number, so our own score is
+/// always the first one.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    /// Number of players in the game.
+    pub players: i32,
+    /// Final score of each player.
+    pub scores: Vec<i32>,
+}
+
+impl GameResult {
+    /// Construct a new, empty `GameResult`.
+    pub fn new() -> GameResult {
+        Default::default()
+    }
+
+    /// Parse the given end of game line and update self.
+    ///
+    /// Only "players" and "score" lines are understood; map lines sent
+    /// after "end" belong to the `World`.
+    pub fn update(&mut self, line: &str) -> Result<()> {
+        let mut split = line.split(' ');
+        let variant = try!(split.next().ok_or(Error::UnexpectedLine));
+        match variant {
+            "players" => {
+                let players = try!(split.next().ok_or(Error::UnknownCommand));
+                self.players = try!(players.parse());
+            },
+            "score" => {
+                let mut scores = Vec::new();
+                for score in split {
+                    scores.push(try!(score.parse()));
+                }
+                self.scores = scores;
+            },
+            _ => return Err(Error::UnknownCommand),
+        }
+        Ok(())
+    }
+
+    /// Final score of the given player, if it was reported.
+    pub fn score(&self, player: Player) -> Option<i32> {
+        let index = match player {
+            Player::Me => 0,
+            Player::Other(n) => n as usize,
+        };
+        self.scores.get(index).cloned()
+    }
+
+    /// Whether we finished with the highest score (ties included).
+    pub fn is_win(&self) -> bool {
+        match self.score(Player::Me) {
+            Some(mine) => self.scores.iter().all(|&score| score <= mine),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::player::Player;
+
+    #[test]
+    fn example() {
+        let mut result = GameResult::new();
+        result.update("players 3").unwrap();
+        result.update("score 4 1 2").unwrap();
+        assert_eq!(result, GameResult {
+            players: 3,
+            scores: vec![4, 1, 2],
+        });
+        assert_eq!(result.score(Player::Other(2)), Some(2));
+        assert!(result.is_win());
+    }
+
+    #[test]
+    fn unknown() {
+        let mut result = GameResult::new();
+        assert!(result.update("a 1 2 0").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Generic 2D storage over the game's toroidal map.
+
+use std::ops::{Index, IndexMut};
+use std::slice;
+use std::iter::Enumerate;
+use ants::point::Point;
+
+/// A value for every tile of a map with fixed dimensions.
+///
+/// A `Grid` is indexed by `Point`, wrapping around the edges. It holds the
+/// `Map`'s tiles, and is also used for per-tile bookkeeping which is not
+/// itself map contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    rows: i32,
+    cols: i32,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    /// Create a new grid, with fixed dimensions, filled with `value`.
+    pub fn new(rows: i32, cols: i32, value: T) -> Grid<T> {
+        Grid {
+            rows: rows,
+            cols: cols,
+            cells: vec![value; (rows * cols) as usize],
+        }
+    }
+
+    /// Set every cell to `value`.
+    pub fn fill(&mut self, value: T) {
+        for cell in self.cells.iter_mut() {
+            *cell = value.clone();
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// Number of rows in the grid.
+    pub fn rows(&self) -> i32 {
+        self.rows
+    }
+
+    /// Number of cols in the grid.
+    pub fn cols(&self) -> i32 {
+        self.cols
+    }
+
+    /// The storage index of the (wrapped) `point`.
+    fn index_of(&self, point: Point) -> usize {
+        let Point{row, col} = point.wrap(self.rows, self.cols);
+        (row * self.cols + col) as usize
+    }
+
+    /// An iterator over every cell with its point, in no particular order.
+    pub fn iter<'a>(&'a self) -> Cells<'a, T> {
+        Cells {
+            cols: self.cols,
+            inner: self.cells.iter().enumerate(),
+        }
+    }
+
+    /// A mut iterator over every cell with its point, in no particular
+    /// order.
+    pub fn iter_mut<'a>(&'a mut self) -> CellsMut<'a, T> {
+        CellsMut {
+            cols: self.cols,
+            inner: self.cells.iter_mut().enumerate(),
+        }
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index<'a>(&'a self, point: Point) -> &'a T {
+        &self.cells[self.index_of(point)]
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut<'a>(&'a mut self, point: Point) -> &'a mut T {
+        let index = self.index_of(point);
+        &mut self.cells[index]
+    }
+}
+
+/// An iterator over every cell of a grid with its point.
+pub struct Cells<'a, T: 'a> {
+    cols: i32,
+    inner: Enumerate<slice::Iter<'a, T>>,
+}
+
+impl<'a, T> Iterator for Cells<'a, T> {
+    type Item = (Point, &'a T);
+
+    fn next(&mut self) -> Option<(Point, &'a T)> {
+        if let Some((i, cell)) = self.inner.next() {
+            Some((Point {
+                row: i as i32 / self.cols,
+                col: i as i32 % self.cols,
+            }, cell))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// A mut iterator over every cell of a grid with its point.
+pub struct CellsMut<'a, T: 'a> {
+    cols: i32,
+    inner: Enumerate<slice::IterMut<'a, T>>,
+}
+
+impl<'a, T> Iterator for CellsMut<'a, T> {
+    type Item = (Point, &'a mut T);
+
+    fn next(&mut self) -> Option<(Point, &'a mut T)> {
+        if let Some((i, cell)) = self.inner.next() {
+            Some((Point {
+                row: i as i32 / self.cols,
+                col: i as i32 % self.cols,
+            }, cell))
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
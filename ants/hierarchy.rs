@@ -0,0 +1,482 @@
+//! Hierarchical pathfinding (HPA*) for large maps.
+//!
+//! The map is cut into square clusters. Where walkable tiles meet across a
+//! cluster border, an entrance is placed, and the shortest paths between
+//! the entrances of each cluster are precomputed. A route is then found by
+//! searching this much smaller abstract graph, and stitched together from
+//! the precomputed paths.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use ants::direction::Direction;
+use ants::grid::Grid;
+use ants::map::{Map, UnknownPolicy};
+use ants::path::a_star;
+use ants::point::Point;
+
+/// Entrances longer than this many tiles get a transition at each end
+/// rather than one in the middle.
+const LONG_ENTRANCE: i32 = 6;
+
+/// The tiles covered by one cluster.
+#[derive(Debug, Copy, Clone)]
+struct Bounds {
+    row: i32,
+    col: i32,
+    rows: i32,
+    cols: i32,
+}
+
+impl Bounds {
+    fn contains(&self, point: Point) -> bool {
+        point.row >= self.row && point.row < self.row + self.rows &&
+            point.col >= self.col && point.col < self.col + self.cols
+    }
+
+    fn index(&self, point: Point) -> usize {
+        ((point.row - self.row) * self.cols + point.col - self.col) as usize
+    }
+
+    fn len(&self) -> usize {
+        (self.rows * self.cols) as usize
+    }
+}
+
+/// An edge of the abstract graph, with the moves which follow it.
+#[derive(Debug, Clone)]
+struct Link {
+    to: Point,
+    path: Vec<Direction>,
+}
+
+impl Link {
+    /// This link as an edge for `a_star`, costing one per move.
+    fn edge(&self) -> (Point, i32, &[Direction]) {
+        (self.to, self.path.len() as i32, &*self.path)
+    }
+}
+
+/// An abstract graph over clusters of the map, for fast long routes.
+///
+/// Build it once with `new`, then call `update` each turn so that it picks
+/// up newly discovered water; only the clusters which changed are
+/// recomputed. Routes found with `find` are near-optimal rather than
+/// shortest, in exchange for searching far fewer nodes than A* on the full
+/// grid.
+#[derive(Debug, Clone)]
+pub struct Hierarchy {
+    rows: i32,
+    cols: i32,
+    size: i32,
+    cluster_rows: i32,
+    cluster_cols: i32,
+    unknown: UnknownPolicy,
+    walkable: Grid<bool>,
+    /// Transitions across the east and south borders of each cluster, as
+    /// pairs of (inside, outside) points.
+    borders: Vec<[Vec<(Point, Point)>; 2]>,
+    /// Paths between the entrances of each cluster.
+    intra: Vec<Vec<(Point, Link)>>,
+    /// Links leaving the entrances inside each cluster, by entrance.
+    graph: Vec<HashMap<Point, Vec<Link>>>,
+}
+
+impl Hierarchy {
+    /// Build the abstract graph for `map`, with clusters of `size` by
+    /// `size` tiles. Unseen tiles are treated according to `unknown`.
+    pub fn new(map: &Map, size: i32, unknown: UnknownPolicy) -> Hierarchy {
+        let (rows, cols) = (map.rows(), map.cols());
+        let size = size.max(1);
+        let cluster_rows = (rows + size - 1) / size;
+        let cluster_cols = (cols + size - 1) / size;
+        let clusters = (cluster_rows * cluster_cols) as usize;
+        let mut hierarchy = Hierarchy {
+            rows: rows,
+            cols: cols,
+            size: size,
+            cluster_rows: cluster_rows,
+            cluster_cols: cluster_cols,
+            unknown: unknown,
+            walkable: Grid::new(rows, cols, false),
+            borders: vec![[Vec::new(), Vec::new()]; clusters],
+            intra: vec![Vec::new(); clusters],
+            graph: vec![HashMap::new(); clusters],
+        };
+        for (point, _) in map.tiles() {
+            hierarchy.walkable[point] = map.is_walkable(point, unknown);
+        }
+        let all: Vec<usize> = (0..clusters).collect();
+        hierarchy.rebuild(&all);
+        hierarchy
+    }
+
+    /// Bring the graph up to date with `map`, recomputing only the clusters
+    /// where walkability changed.
+    pub fn update(&mut self, map: &Map) {
+        let mut dirty = HashSet::new();
+        for (point, _) in map.tiles() {
+            let walkable = map.is_walkable(point, self.unknown);
+            if self.walkable[point] != walkable {
+                self.walkable[point] = walkable;
+                dirty.insert(self.cluster_of(point));
+            }
+        }
+        if !dirty.is_empty() {
+            let dirty: Vec<usize> = dirty.into_iter().collect();
+            self.rebuild(&dirty);
+        }
+    }
+
+    /// Number of entrance nodes in the abstract graph.
+    pub fn node_count(&self) -> usize {
+        self.graph.iter().map(|nodes| nodes.len()).sum()
+    }
+
+    /// Number of directed edges in the abstract graph.
+    pub fn edge_count(&self) -> usize {
+        self.graph.iter()
+            .flat_map(|nodes| nodes.values())
+            .map(|links| links.len())
+            .sum()
+    }
+
+    /// A route from `from` to `to`, as the moves to make in order.
+    ///
+    /// Returns an empty route if `from` is `to`, and `None` if `to` cannot
+    /// be reached as of the last `update`.
+    pub fn find(&self, from: Point, to: Point) -> Option<Vec<Direction>> {
+        let from = from.wrap(self.rows, self.cols);
+        let to = to.wrap(self.rows, self.cols);
+        if from == to {
+            return Some(Vec::new());
+        }
+        if !self.walkable[to] {
+            return None;
+        }
+        let start = self.cluster_of(from);
+        let goal = self.cluster_of(to);
+        if start == goal {
+            let flood = self.flood(start, from);
+            if let Some(path) = self.walk_back(start, &flood, from, to) {
+                return Some(path);
+            }
+        }
+
+        // temporary links from the start to its cluster's entrances, and
+        // from the goal cluster's entrances to the goal
+        let mut starts = Vec::new();
+        let flood = self.flood(start, from);
+        for node in self.nodes(start) {
+            if let Some(path) = self.walk_back(start, &flood, from, node) {
+                starts.push(Link { to: node, path: path });
+            }
+        }
+        let mut goals = HashMap::new();
+        let flood = self.flood(goal, to);
+        for node in self.nodes(goal) {
+            if let Some(path) = self.walk_back(goal, &flood, to, node) {
+                goals.insert(node, reverse(&path));
+            }
+        }
+
+        let segments = a_star(from, to, |point| {
+            let mut next: Vec<(Point, i32, &[Direction])> = Vec::new();
+            if point == from {
+                next.extend(starts.iter().map(Link::edge));
+            }
+            // the start may itself be an entrance
+            let cluster = self.cluster_of(point);
+            if let Some(links) = self.graph[cluster].get(&point) {
+                next.extend(links.iter().map(Link::edge));
+            }
+            if let Some(path) = goals.get(&point) {
+                next.push((to, path.len() as i32, &**path));
+            }
+            next
+        }, |point| self.estimate(point, to));
+        segments.map(|segments| segments.concat())
+    }
+
+    fn estimate(&self, from: Point, to: Point) -> i32 {
+        from.manhattan(to, self.rows, self.cols)
+    }
+
+    fn cluster_of(&self, point: Point) -> usize {
+        let point = point.wrap(self.rows, self.cols);
+        ((point.row / self.size) * self.cluster_cols +
+         point.col / self.size) as usize
+    }
+
+    fn bounds(&self, cluster: usize) -> Bounds {
+        let row = cluster as i32 / self.cluster_cols * self.size;
+        let col = cluster as i32 % self.cluster_cols * self.size;
+        Bounds {
+            row: row,
+            col: col,
+            rows: self.size.min(self.rows - row),
+            cols: self.size.min(self.cols - col),
+        }
+    }
+
+    /// The clusters to the (north, south, east, west) of `cluster`.
+    fn neighbors(&self, cluster: usize) -> [usize; 4] {
+        let (crows, ccols) = (self.cluster_rows, self.cluster_cols);
+        let row = cluster as i32 / ccols;
+        let col = cluster as i32 % ccols;
+        let at = |row: i32, col: i32| {
+            (((row + crows) % crows) * ccols + (col + ccols) % ccols) as usize
+        };
+        [at(row - 1, col), at(row + 1, col), at(row, col + 1), at(row, col - 1)]
+    }
+
+    /// The entrance nodes inside `cluster`.
+    fn nodes(&self, cluster: usize) -> Vec<Point> {
+        let [north, _, _, west] = self.neighbors(cluster);
+        let mut nodes = Vec::new();
+        for &(inside, _) in self.borders[cluster].iter().flat_map(|b| b) {
+            nodes.push(inside);
+        }
+        for &(_, outside) in self.borders[west][0].iter() {
+            nodes.push(outside);
+        }
+        for &(_, outside) in self.borders[north][1].iter() {
+            nodes.push(outside);
+        }
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
+
+    /// Recompute the borders, entrance paths and links around `dirty`
+    /// clusters.
+    fn rebuild(&mut self, dirty: &[usize]) {
+        let mut borders = HashSet::new();
+        let mut clusters = HashSet::new();
+        for &cluster in dirty {
+            let [north, south, east, west] = self.neighbors(cluster);
+            borders.insert((cluster, 0));
+            borders.insert((cluster, 1));
+            borders.insert((west, 0));
+            borders.insert((north, 1));
+            clusters.extend([cluster, north, south, east, west].iter());
+        }
+        for (cluster, side) in borders {
+            self.borders[cluster][side] = self.transitions(cluster, side);
+        }
+        // a cluster's links only depend on its own paths and the borders
+        // around it, all of which are covered by `clusters`
+        for cluster in clusters {
+            self.intra[cluster] = self.entrance_paths(cluster);
+            self.graph[cluster] = self.links(cluster);
+        }
+    }
+
+    /// The links leaving each entrance inside `cluster`: across its borders
+    /// and along its entrance paths.
+    fn links(&self, cluster: usize) -> HashMap<Point, Vec<Link>> {
+        let [north, _, _, west] = self.neighbors(cluster);
+        let mut across = Vec::new();
+        for (side, border) in self.borders[cluster].iter().enumerate() {
+            let direction = [Direction::East, Direction::South][side];
+            across.extend(border.iter().map(|&(inside, outside)| {
+                (inside, outside, direction)
+            }));
+        }
+        across.extend(self.borders[west][0].iter().map(|&(inside, outside)| {
+            (outside, inside, Direction::West)
+        }));
+        across.extend(self.borders[north][1].iter().map(|&(inside, outside)| {
+            (outside, inside, Direction::North)
+        }));
+
+        let mut links: HashMap<Point, Vec<Link>> = HashMap::new();
+        for (from, to, direction) in across {
+            links.entry(from).or_insert(Vec::new()).push(Link {
+                to: to,
+                path: vec![direction],
+            });
+        }
+        for &(from, ref link) in self.intra[cluster].iter() {
+            links.entry(from).or_insert(Vec::new()).push(link.clone());
+        }
+        links
+    }
+
+    /// Find the transitions across the east (`side` 0) or south (`side` 1)
+    /// border of `cluster`.
+    fn transitions(&self, cluster: usize, side: usize) -> Vec<(Point, Point)> {
+        let bounds = self.bounds(cluster);
+        let (step, across, len) = if side == 0 {
+            (Point { row: 1, col: 0 },
+             Point { row: bounds.row, col: bounds.col + bounds.cols - 1 },
+             bounds.rows)
+        } else {
+            (Point { row: 0, col: 1 },
+             Point { row: bounds.row + bounds.rows - 1, col: bounds.col },
+             bounds.cols)
+        };
+        let out = if side == 0 { Direction::East } else { Direction::South };
+        let at = |i: i32| {
+            let inside = Point {
+                row: across.row + step.row * i,
+                col: across.col + step.col * i,
+            };
+            (inside, (inside + out).wrap(self.rows, self.cols))
+        };
+        let mut transitions = Vec::new();
+        let mut run_start = None;
+        for i in 0..len+1 {
+            let open = i < len && {
+                let (inside, outside) = at(i);
+                self.walkable[inside] && self.walkable[outside]
+            };
+            match (open, run_start) {
+                (true, None) => run_start = Some(i),
+                (false, Some(start)) => {
+                    let end = i - 1;
+                    if end - start + 1 >= LONG_ENTRANCE {
+                        transitions.push(at(start));
+                        transitions.push(at(end));
+                    } else {
+                        transitions.push(at((start + end) / 2));
+                    }
+                    run_start = None;
+                },
+                _ => {},
+            }
+        }
+        transitions
+    }
+
+    /// Shortest paths within `cluster` between each pair of its entrances.
+    fn entrance_paths(&self, cluster: usize) -> Vec<(Point, Link)> {
+        let nodes = self.nodes(cluster);
+        let mut paths = Vec::new();
+        for &from in nodes.iter() {
+            let flood = self.flood(cluster, from);
+            for &to in nodes.iter() {
+                if from == to {
+                    continue;
+                }
+                if let Some(path) = self.walk_back(cluster, &flood, from, to) {
+                    paths.push((from, Link { to: to, path: path }));
+                }
+            }
+        }
+        paths
+    }
+
+    /// Breadth-first search from `start` which stays inside `cluster`.
+    ///
+    /// Returns, for each tile of the cluster, the move by which it was
+    /// first reached, if it was.
+    fn flood(&self, cluster: usize, start: Point) -> Vec<Option<Direction>> {
+        let bounds = self.bounds(cluster);
+        let mut came = vec![None; bounds.len()];
+        let mut seen = vec![false; bounds.len()];
+        let mut queue = VecDeque::new();
+        seen[bounds.index(start)] = true;
+        queue.push_back(start);
+        while let Some(point) = queue.pop_front() {
+            for &direction in Direction::ALL.iter() {
+                let next = point + direction;
+                if bounds.contains(next) && !seen[bounds.index(next)] &&
+                        self.walkable[next] {
+                    seen[bounds.index(next)] = true;
+                    came[bounds.index(next)] = Some(direction);
+                    queue.push_back(next);
+                }
+            }
+        }
+        came
+    }
+
+    /// The path from `start` to `to` found by `flood`, if `to` was reached.
+    fn walk_back(&self, cluster: usize, flood: &[Option<Direction>],
+                 start: Point, to: Point) -> Option<Vec<Direction>> {
+        let bounds = self.bounds(cluster);
+        let mut path = Vec::new();
+        let mut point = to;
+        while point != start {
+            let direction = match flood[bounds.index(point)] {
+                Some(direction) => direction,
+                None => return None,
+            };
+            path.push(direction);
+            point = point + direction.opposite();
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// The moves which retrace `path` from its end back to its start.
+fn reverse(path: &[Direction]) -> Vec<Direction> {
+    path.iter().rev().map(|d| d.opposite()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::path::{find_path, is_path_clear};
+    use ants::tile::Tile;
+
+    #[test]
+    fn find() {
+        let map = Map::filled(20, 20, Tile::land());
+        let hierarchy = Hierarchy::new(&map, 5, UnknownPolicy::Blocked);
+        let from = Point { row: 1, col: 1 };
+        let to = Point { row: 12, col: 17 };
+        let path = hierarchy.find(from, to).unwrap();
+        let mut point = from;
+        for &direction in path.iter() {
+            point = (point + direction).wrap(20, 20);
+        }
+        assert_eq!(point, to);
+        let shortest = find_path(&map, from, to, UnknownPolicy::Blocked);
+        assert!(path.len() <= shortest.unwrap().len() + 4);
+    }
+
+    #[test]
+    fn find_from_entrance() {
+        // the only way between the two halves is the entrance at 2,4
+        let mut map = Map::filled(10, 10, Tile::land());
+        for row in 0..10 {
+            map[Point { row: row, col: 0 }] = Tile::water();
+            if row != 2 {
+                map[Point { row: row, col: 5 }] = Tile::water();
+            }
+        }
+        let hierarchy = Hierarchy::new(&map, 5, UnknownPolicy::Blocked);
+        let from = Point { row: 2, col: 4 };
+        let to = Point { row: 2, col: 7 };
+        assert_eq!(hierarchy.find(from, to), Some(vec![Direction::East; 3]));
+        assert_eq!(hierarchy.find(to, from), Some(vec![Direction::West; 3]));
+
+        let map = Map::filled(10, 10, Tile::land());
+        let hierarchy = Hierarchy::new(&map, 5, UnknownPolicy::Blocked);
+        assert_eq!(hierarchy.find(from, to).map(|path| path.len()), Some(3));
+    }
+
+    #[test]
+    fn update() {
+        let mut map = Map::filled(10, 10, Tile::land());
+        let mut hierarchy = Hierarchy::new(&map, 5, UnknownPolicy::Blocked);
+        let from = Point { row: 2, col: 2 };
+        let to = Point { row: 2, col: 7 };
+        // wall off the target's column of clusters
+        for row in 0..10 {
+            map[Point { row: row, col: 5 }] = Tile::water();
+            map[Point { row: row, col: 0 }] = Tile::water();
+        }
+        hierarchy.update(&map);
+        assert_eq!(hierarchy.find(from, to), None);
+        map[Point { row: 8, col: 5 }] = Tile::land();
+        hierarchy.update(&map);
+        let path = hierarchy.find(from, to).unwrap();
+        assert!(is_path_clear(&map, from, &path, UnknownPolicy::Blocked));
+        // only some clusters were rebuilt, but the graph matches a new one
+        let fresh = Hierarchy::new(&map, 5, UnknownPolicy::Blocked);
+        assert_eq!(hierarchy.node_count(), fresh.node_count());
+        assert_eq!(hierarchy.edge_count(), fresh.edge_count());
+    }
+}
@@ -1,117 +1,96 @@
 //! 2D representation of the game world's map.
 
 use std::ops::{Index, IndexMut};
-use std::slice;
-use std::iter::Enumerate;
+use ants::grid::{Grid, Cells, CellsMut};
 use ants::point::Point;
-use ants::tile::Tile;
+use ants::tile::{Tile, Terrain};
+
+/// How searches over the map treat tiles which have never been seen.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnknownPolicy {
+    /// Assume unseen tiles are land, which is optimistic but explores.
+    Passable,
+    /// Avoid unseen tiles, as they might be water.
+    Blocked,
+}
+
+impl Default for UnknownPolicy {
+    fn default() -> UnknownPolicy {
+        UnknownPolicy::Passable
+    }
+}
 
-/// 2D collection of `Tile` with fixed dimensions and visibility.
+/// 2D collection of `Tile` with fixed dimensions.
 ///
-/// Visibility is represented by wrapping each `Tile` in an `Option`: `None`
-/// represents a tile which is not visible, and `Some(..)` wraps one which is.
+/// The map holds what is known of each tile: its terrain once it has been
+/// seen, and whatever was reported on it this turn. Whether a tile is
+/// visible this turn is tracked separately by the `World`.
 #[derive(Debug)]
 pub struct Map {
-    rows: i32,
-    cols: i32,
-    tiles: Vec<Option<Tile>>,
+    tiles: Grid<Tile>,
 }
 
 impl Map {
     /// Create a new map, with fixed dimensions.
     pub fn new(rows: i32, cols: i32) -> Map {
+        Map::filled(rows, cols, Tile::default())
+    }
+
+    /// Create a new map, with fixed dimensions, with every tile set to
+    /// `tile`; e.g. all land, for a map built by hand.
+    pub fn filled(rows: i32, cols: i32, tile: Tile) -> Map {
         Map {
-            rows: rows,
-            cols: cols,
-            tiles: vec![None; (rows * cols) as usize],
+            tiles: Grid::new(rows, cols, tile),
+        }
+    }
+
+    /// Number of rows in the map.
+    pub fn rows(&self) -> i32 {
+        self.tiles.rows()
+    }
+
+    /// Number of cols in the map.
+    pub fn cols(&self) -> i32 {
+        self.tiles.cols()
+    }
+
+    /// Whether an ant could walk onto the tile at `point`, with unseen
+    /// tiles treated according to `unknown`.
+    pub fn is_walkable(&self, point: Point, unknown: UnknownPolicy) -> bool {
+        match self[point].terrain {
+            Terrain::Land => true,
+            Terrain::Water => false,
+            Terrain::Unknown => unknown == UnknownPolicy::Passable,
         }
     }
 
     /// An iterator over all tiles in the map, in no particular order.
     pub fn tiles<'a>(&'a self) -> Tiles<'a> {
-        Tiles {
-            rows: self.rows,
-            cols: self.cols,
-            inner: self.tiles.iter().enumerate(),
-        }
+        self.tiles.iter()
     }
 
     /// A mut iterator over all tiles in the map, in no particular order.
     pub fn tiles_mut<'a>(&'a mut self) -> TilesMut<'a> {
-        TilesMut {
-            rows: self.rows,
-            cols: self.cols,
-            inner: self.tiles.iter_mut().enumerate(),
-        }
+        self.tiles.iter_mut()
     }
 }
 
 impl Index<Point> for Map {
-    type Output = Option<Tile>;
+    type Output = Tile;
 
-    fn index<'a>(&'a self, point: Point) -> &'a Option<Tile> {
-        let Point{row, col} = point.wrap(self.rows, self.cols);
-        &self.tiles[(row * self.cols + col) as usize]
+    fn index<'a>(&'a self, point: Point) -> &'a Tile {
+        &self.tiles[point]
     }
 }
 
 impl IndexMut<Point> for Map {
-    fn index_mut<'a>(&'a mut self, point: Point) -> &'a mut Option<Tile> {
-        let Point{row, col} = point.wrap(self.rows, self.cols);
-        &mut self.tiles[(row * self.cols + col) as usize]
+    fn index_mut<'a>(&'a mut self, point: Point) -> &'a mut Tile {
+        &mut self.tiles[point]
     }
 }
 
 /// An iterator over all tiles in the map, in no particular order.
-pub struct Tiles<'a> {
-    rows: i32,
-    cols: i32,
-    inner: Enumerate<slice::Iter<'a, Option<Tile>>>,
-}
-
-impl<'a> Iterator for Tiles<'a> {
-    type Item = (Point, &'a Option<Tile>);
-
-    fn next(&mut self) -> Option<(Point, &'a Option<Tile>)> {
-        if let Some((i, tile)) = self.inner.next() {
-            Some((Point {
-                row: i as i32 / self.cols,
-                col: i as i32 % self.cols,
-            }, tile))
-        } else {
-            None
-        }
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let size = (self.rows as usize) * (self.cols as usize);
-        (size, Some(size))
-    }
-}
+pub type Tiles<'a> = Cells<'a, Tile>;
 
 /// A mut iterator over all tiles in the map, in no particular order.
-pub struct TilesMut<'a> {
-    rows: i32,
-    cols: i32,
-    inner: Enumerate<slice::IterMut<'a, Option<Tile>>>,
-}
-
-impl<'a> Iterator for TilesMut<'a> {
-    type Item = (Point, &'a mut Option<Tile>);
-
-    fn next(&mut self) -> Option<(Point, &'a mut Option<Tile>)> {
-        if let Some((i, tile)) = self.inner.next() {
-            Some((Point {
-                row: i as i32 / self.cols,
-                col: i as i32 % self.cols,
-            }, tile))
-        } else {
-            None
-        }
-    }
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let size = (self.rows as usize) * (self.cols as usize);
-        (size, Some(size))
-    }
-}
+pub type TilesMut<'a> = CellsMut<'a, Tile>;
@@ -0,0 +1,137 @@
+//! What the bot remembers of the world between turns.
+
+use std::collections::HashMap;
+use ants::grid::Grid;
+use ants::map::Map;
+use ants::player::Player;
+use ants::point::Point;
+use ants::tile::Tile;
+
+/// A remembered sighting of something on the map.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Sighting {
+    /// Where it was seen.
+    pub point: Point,
+    /// The last turn on which it was seen.
+    pub turn: i32,
+    /// How many turns ago it was last seen.
+    pub age: i32,
+}
+
+/// Memory of every tile the bot has seen, kept across turns.
+///
+/// The map only holds what is visible on the current turn; `Memory` keeps
+/// what was last seen everywhere else. Remembered food and hills are
+/// forgotten once their tile is seen again without them.
+#[derive(Debug)]
+pub struct Memory {
+    turn: i32,
+    last_seen: Grid<Option<i32>>,
+    last_known: Grid<Option<Tile>>,
+    hills: HashMap<Point, (Player, i32)>,
+    food: HashMap<Point, i32>,
+}
+
+impl Memory {
+    /// Create a new, empty memory for a map with the given dimensions.
+    pub fn new(rows: i32, cols: i32) -> Memory {
+        Memory {
+            turn: 0,
+            last_seen: Grid::new(rows, cols, None),
+            last_known: Grid::new(rows, cols, None),
+            hills: HashMap::new(),
+            food: HashMap::new(),
+        }
+    }
+
+    /// Remember everything on `map` which is `visible` during `turn`.
+    pub fn remember(&mut self, map: &Map, visible: &Grid<bool>, turn: i32) {
+        self.turn = turn;
+        for (point, &tile) in map.tiles() {
+            if !visible[point] {
+                continue;
+            }
+            self.last_seen[point] = Some(turn);
+            self.last_known[point] = Some(tile);
+            if tile.food {
+                self.food.insert(point, turn);
+            } else {
+                self.food.remove(&point);
+            }
+            match tile.hill {
+                Some(owner) => {
+                    self.hills.insert(point, (owner, turn));
+                },
+                None => {
+                    self.hills.remove(&point);
+                },
+            }
+        }
+    }
+
+    /// The last turn on which the tile at `point` was visible, if ever.
+    pub fn last_seen(&self, point: Point) -> Option<i32> {
+        self.last_seen[point]
+    }
+
+    /// What was on the tile at `point` when it was last visible, if ever.
+    pub fn last_known(&self, point: Point) -> Option<Tile> {
+        self.last_known[point]
+    }
+
+    /// How many turns ago the tile at `point` was last visible, if ever.
+    pub fn age(&self, point: Point) -> Option<i32> {
+        self.last_seen[point].map(|turn| self.turn - turn)
+    }
+
+    /// All hills which are remembered, with their owners.
+    pub fn hills(&self) -> Vec<(Sighting, Player)> {
+        self.hills.iter().map(|(&point, &(owner, turn))| {
+            (self.sighting(point, turn), owner)
+        }).collect()
+    }
+
+    /// All food which is remembered.
+    pub fn food(&self) -> Vec<Sighting> {
+        self.food.iter().map(|(&point, &turn)| {
+            self.sighting(point, turn)
+        }).collect()
+    }
+
+    fn sighting(&self, point: Point, turn: i32) -> Sighting {
+        Sighting {
+            point: point,
+            turn: turn,
+            age: self.turn - turn,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remember() {
+        let food = Point { row: 1, col: 1 };
+        let hill = Point { row: 2, col: 2 };
+        let mut map = Map::new(4, 4);
+        let mut visible = Grid::new(4, 4, false);
+        let mut memory = Memory::new(4, 4);
+        map[food] = Tile { food: true, ..Tile::land() };
+        map[hill] = Tile { hill: Some(Player::Other(1)), ..Tile::land() };
+        visible[food] = true;
+        visible[hill] = true;
+        memory.remember(&map, &visible, 1);
+        map[food].clear();
+        map[hill].clear();
+        visible[food] = false;
+        memory.remember(&map, &visible, 3);
+        let sighting = Sighting { point: food, turn: 1, age: 2 };
+        assert_eq!(memory.food(), vec![sighting]);
+        assert_eq!(memory.hills(), vec![]);
+        assert!(memory.last_known(food).unwrap().food);
+        assert_eq!(memory.age(hill), Some(0));
+        assert_eq!(memory.last_seen(Point { row: 0, col: 0 }), None);
+    }
+}
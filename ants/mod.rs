@@ -7,12 +7,9 @@
 //! ```
 //! extern crate ants;
 //!
-//! use ants::ant::Ant;
 //! use ants::bot::Bot;
 //! use ants::direction::Direction;
 //! use ants::error::Result;
-//! use ants::player::Player;
-//! use ants::tile::Tile;
 //! use ants::world::World;
 //!
 //! struct MyBot;
@@ -20,15 +17,10 @@
 //! impl Bot for MyBot {
 //!     fn do_turn(&mut self, world: &mut World) -> Result<()> {
 //!         let direction = Direction::North;
-//!         for point in world.map.tiles().filter_map(|(point, &tile)| {
-//!                     if tile == Some(Tile::Ant(Ant{ alive: true, owner: Player::Me })) {
-//!                         Some(point)
-//!                     } else {
-//!                         None
-//!                     }
-//!                 }) {
-//!             if world.map[point + direction].unwrap_or(Tile::Land).is_passable() {
-//!                 world.order(point, direction);
+//!         let ants = world.my_ants().to_vec();
+//!         for point in ants {
+//!             if world.map[point + direction].is_passable() {
+//!                 try!(world.order(point, direction));
 //!             }
 //!         }
 //!         Ok(())
@@ -49,7 +41,21 @@ pub mod world;
 pub mod tile;
 pub mod point;
 pub mod player;
-pub mod ant;
 pub mod direction;
 pub mod map;
 pub mod bot;
+pub mod game_result;
+pub mod order;
+pub mod clock;
+pub mod grid;
+pub mod memory;
+pub mod offsets;
+pub mod distance;
+pub mod path;
+pub mod hierarchy;
+pub mod diffusion;
+pub mod combat;
+pub mod battle;
+pub mod threat;
+pub mod moves;
+pub mod food;
@@ -0,0 +1,151 @@
+//! Collision-free moves for all of our ants.
+//!
+//! Each ant asks for its moves in order of preference. Ants may follow each
+//! other in a chain or swap squares, since the engine moves every ant at
+//! once, but no two may end up on the same square. An ant which stays put
+//! keeps its square, so an ant moving onto it is bumped to its next choice.
+
+use std::collections::HashSet;
+use ants::direction::Direction;
+use ants::map::{Map, UnknownPolicy};
+use ants::order::Order;
+use ants::point::Point;
+
+/// Choose one move for each ant in `moves`, with no two ants colliding.
+///
+/// Each entry gives an ant's point and its preferred directions, best
+/// first. Moves into tiles which are not walkable under `unknown` are
+/// skipped. When several ants want the same square, an ant staying there
+/// keeps it, otherwise the ant listed first in `moves` does; the others
+/// fall back to their next preference, and stay put once they have none
+/// left. This is repeated until nobody is bumped.
+///
+/// Squares in `held` are already taken, such as by ants not in `moves`
+/// which stay put or have been ordered there, and no ant is moved onto
+/// them.
+///
+/// Returns orders for the ants which move; the rest stay put.
+pub fn resolve(map: &Map, moves: &[(Point, Vec<Direction>)], held: &[Point],
+               unknown: UnknownPolicy) -> Vec<Order> {
+    let (rows, cols) = (map.rows(), map.cols());
+    let froms: Vec<Point> = moves.iter()
+        .map(|&(from, _)| from.wrap(rows, cols))
+        .collect();
+    // index into each ant's preferences; past the end means staying
+    let mut choices = vec![0; moves.len()];
+    let destination = |i: usize, choice: usize| -> Option<Point> {
+        moves[i].1.get(choice).map(|&d| (froms[i] + d).wrap(rows, cols))
+    };
+
+    let mut bumped = true;
+    while bumped {
+        bumped = false;
+        let mut taken: HashSet<Point> = held.iter()
+            .map(|point| point.wrap(rows, cols))
+            .collect();
+        // stayers claim their squares before any mover
+        for i in 0..moves.len() {
+            while let Some(to) = destination(i, choices[i]) {
+                if map.is_walkable(to, unknown) {
+                    break;
+                }
+                choices[i] += 1;
+            }
+            if destination(i, choices[i]).is_none() {
+                taken.insert(froms[i]);
+            }
+        }
+        for i in 0..moves.len() {
+            if let Some(to) = destination(i, choices[i]) {
+                if !taken.insert(to) {
+                    choices[i] += 1;
+                    bumped = true;
+                }
+            }
+        }
+    }
+
+    (0..moves.len())
+        .filter_map(|i| moves[i].1.get(choices[i]).map(|&direction| {
+            Order { from: froms[i], direction: direction }
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::tile::Tile;
+    use ants::direction::Direction::{North, East, South, West};
+
+    fn map() -> Map {
+        let mut map = Map::filled(10, 10, Tile::land());
+        map[Point { row: 0, col: 5 }] = Tile::water();
+        map
+    }
+
+    fn point(row: i32, col: i32) -> Point {
+        Point { row: row, col: col }
+    }
+
+    fn order(row: i32, col: i32, direction: Direction) -> Order {
+        Order { from: point(row, col), direction: direction }
+    }
+
+    #[test]
+    fn bumps_to_next_choice() {
+        let moves = [
+            (point(5, 5), vec![East]),
+            (point(5, 7), vec![West, North]),
+            (point(0, 4), vec![East, South]),
+        ];
+        let unknown = UnknownPolicy::Passable;
+        assert_eq!(resolve(&map(), &moves, &[], unknown), vec![
+            order(5, 5, East),
+            order(5, 7, North),
+            order(0, 4, South),
+        ]);
+    }
+
+    #[test]
+    fn stayers_keep_their_square() {
+        // the ant at 5,6 has nowhere to go, so the one behind stays too
+        let moves = [
+            (point(5, 5), vec![East]),
+            (point(5, 6), vec![]),
+            (point(5, 4), vec![East]),
+        ];
+        let unknown = UnknownPolicy::Passable;
+        assert_eq!(resolve(&map(), &moves, &[], unknown), vec![]);
+    }
+
+    #[test]
+    fn chains_and_swaps() {
+        let moves = [
+            (point(5, 3), vec![East]),
+            (point(5, 4), vec![East]),
+            (point(5, 5), vec![East]),
+            (point(2, 2), vec![South]),
+            (point(3, 2), vec![North]),
+        ];
+        let unknown = UnknownPolicy::Passable;
+        assert_eq!(resolve(&map(), &moves, &[], unknown), vec![
+            order(5, 3, East),
+            order(5, 4, East),
+            order(5, 5, East),
+            order(2, 2, South),
+            order(3, 2, North),
+        ]);
+    }
+
+    #[test]
+    fn held_squares() {
+        let moves = [
+            (point(5, 5), vec![East, South]),
+            (point(2, 2), vec![South]),
+        ];
+        let held = [point(5, 6), point(3, 2)];
+        assert_eq!(resolve(&map(), &moves, &held, UnknownPolicy::Passable),
+                   vec![order(5, 5, South)]);
+    }
+}
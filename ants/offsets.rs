@@ -0,0 +1,136 @@
+//! Sets of offsets covering the game's radii.
+
+use std::collections::HashSet;
+use std::slice;
+use ants::direction::Direction;
+use ants::point::Point;
+
+/// The offsets of all points within some area around an origin.
+///
+/// Built from a squared radius, as used by the game for view, attack and
+/// spawn ranges, and cached since the offsets are valid for the whole game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetSet {
+    offsets: Box<[Point]>,
+}
+
+impl OffsetSet {
+    /// Calculate the offsets of all points within `radius2` (squared
+    /// euclidean distance) of the origin, the origin included.
+    pub fn new(radius2: i32) -> OffsetSet {
+        let mut offsets = Vec::new();
+        let mx = (radius2.max(0) as f64).sqrt() as i32;
+        for d_row in -mx..mx+1 {
+            for d_col in -mx..mx+1 {
+                if d_row * d_row + d_col * d_col <= radius2 {
+                    offsets.push(Point {
+                        row: d_row,
+                        col: d_col,
+                    });
+                }
+            }
+        }
+        OffsetSet {
+            offsets: offsets.into_boxed_slice(),
+        }
+    }
+
+    /// The offsets reachable from this set with at most one more move.
+    ///
+    /// For the attack radius, this is every point an ant could attack next
+    /// turn.
+    pub fn expand(&self) -> OffsetSet {
+        let mut seen = HashSet::new();
+        let mut offsets = Vec::new();
+        for &offset in self.offsets.iter() {
+            let moved = Direction::ALL.iter().map(|&d| offset + d);
+            for point in Some(offset).into_iter().chain(moved) {
+                if seen.insert(point) {
+                    offsets.push(point);
+                }
+            }
+        }
+        OffsetSet {
+            offsets: offsets.into_boxed_slice(),
+        }
+    }
+
+    /// All offsets in the set, in no particular order.
+    pub fn offsets(&self) -> &[Point] {
+        &self.offsets
+    }
+
+    /// Number of offsets in the set.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether `offset` is in the set.
+    pub fn contains(&self, offset: Point) -> bool {
+        self.offsets.contains(&offset)
+    }
+
+    /// An iterator over the wrapped points around `center` covered by the
+    /// set, on a map of the given dimensions.
+    ///
+    /// On maps smaller than the set, a point may be yielded more than once.
+    pub fn around<'a>(&'a self, center: Point, rows: i32, cols: i32)
+            -> Around<'a> {
+        Around {
+            center: center,
+            rows: rows,
+            cols: cols,
+            inner: self.offsets.iter(),
+        }
+    }
+}
+
+/// An iterator over the wrapped points covered by an `OffsetSet`.
+pub struct Around<'a> {
+    center: Point,
+    rows: i32,
+    cols: i32,
+    inner: slice::Iter<'a, Point>,
+}
+
+impl<'a> Iterator for Around<'a> {
+    type Item = Point;
+
+    fn next(&mut self) -> Option<Point> {
+        self.inner.next().map(|&offset| {
+            (self.center + offset).wrap(self.rows, self.cols)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        assert_eq!(OffsetSet::new(0).offsets(), &[Point { row: 0, col: 0 }]);
+        assert_eq!(OffsetSet::new(1).len(), 5);
+        assert_eq!(OffsetSet::new(5).len(), 21);
+    }
+
+    #[test]
+    fn expand() {
+        let attack = OffsetSet::new(1).expand();
+        assert_eq!(attack.len(), 13);
+        assert!(attack.contains(Point { row: 1, col: 1 }));
+        assert!(!attack.contains(Point { row: 2, col: 1 }));
+    }
+
+    #[test]
+    fn around() {
+        let points: Vec<_> = OffsetSet::new(1)
+            .around(Point { row: 0, col: 0 }, 4, 4).collect();
+        assert!(points.contains(&Point { row: 3, col: 0 }));
+        assert!(points.contains(&Point { row: 0, col: 3 }));
+    }
+}
@@ -0,0 +1,55 @@
+//! Orders issued to our ants.
+
+use std::fmt;
+use ants::direction::Direction;
+use ants::point::Point;
+
+/// An order for the ant at `from` to move one step in `direction`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Order {
+    /// Where the ordered ant currently stands.
+    pub from: Point,
+    /// Which way the ant should move.
+    pub direction: Direction,
+}
+
+impl Order {
+    /// Where the ant will end up, before wrapping to the map's dimensions.
+    pub fn to(&self) -> Point {
+        self.from + self.direction
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "o {} {}", self.from, self.direction)
+    }
+}
+
+/// Why an order was refused by `World`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    /// There is no live ant of ours at the order's origin.
+    NoAnt,
+    /// The destination is water.
+    Water,
+    /// An order was already issued for this ant this turn.
+    Duplicate,
+    /// Another of our ants is already ordered onto the same destination.
+    ///
+    /// This is only checked in strict mode.
+    Collision,
+}
+
+impl ::std::error::Error for OrderError {}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}", match *self {
+            OrderError::NoAnt => "No ant to order",
+            OrderError::Water => "Ordered into water",
+            OrderError::Duplicate => "Ant already ordered",
+            OrderError::Collision => "Ordered into collision",
+        })
+    }
+}
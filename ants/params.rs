@@ -1,7 +1,8 @@
 //! Constant parameters set once per game.
 
+use std::collections::HashMap;
 use std::default::Default;
-use std::io::BufRead;
+use std::io::{self, BufRead};
 use ants::error::{Result, Error};
 
 /// Parameters supplied once before the game begins.
@@ -16,6 +17,10 @@ pub struct Params {
     pub attackradius2: i32,
     pub spawnradius2: i32,
     pub player_seed: i64,
+    pub players: i32,
+    /// Parameters this crate does not know about, by name, with their raw
+    /// values.
+    pub extra: HashMap<String, String>,
 }
 
 impl Params {
@@ -27,27 +32,68 @@ impl Params {
     /// Parse input on `read` to populate a `Params`.
     ///
     /// Input must start with the line "turn 0", end with the line "ready",
-    /// and have only valid parameter commands in-between.
+    /// and have only parameter commands in-between. Unknown parameters are
+    /// kept in `extra`; the known ones are checked with `validate`.
     pub fn from_buf_read<R: BufRead>(read: R) -> Result<Params> {
-        let mut lines = read.lines();
-        let first_line = try!(try!(lines.next().ok_or(Error::UnexpectedEof)));
+        Params::from_lines(read.lines())
+    }
+
+    /// Parse the given input lines to populate a `Params`.
+    ///
+    /// As `from_buf_read`, but lines after "ready" are left in `lines`.
+    /// Parse errors are given line numbers counting from the first line
+    /// taken from `lines`.
+    pub fn from_lines<I>(lines: I) -> Result<Params>
+            where I: Iterator<Item = io::Result<String>> {
+        let mut lines = lines.enumerate();
+        let (_, first_line) = try!(lines.next().ok_or(Error::UnexpectedEof));
+        let first_line = try!(first_line);
         if &*first_line != "turn 0" {
-            return Err(Error::UnexpectedLine);
+            return Err(Error::parse(0, &*first_line, Error::UnexpectedLine)
+                       .at_line(1));
         }
         let mut params = Params::new();
-        for line in lines {
+        for (i, line) in lines {
             let line = try!(line);
             if line == "ready" {
                 break;
             } else {
-                try!(params.update(&*line));
+                try!(params.update(&*line).map_err(|e| e.at_line(i + 1)));
             }
         }
+        try!(params.validate());
         Ok(params)
     }
 
-    /// Parse the given line for valid parameter commands and update self.
+    /// Check that the parameters describe a playable game.
+    ///
+    /// The map must have rows and cols, and every radius must be positive.
+    pub fn validate(&self) -> Result<()> {
+        let checks = [
+            ("rows", self.rows),
+            ("cols", self.cols),
+            ("viewradius2", self.viewradius2),
+            ("attackradius2", self.attackradius2),
+            ("spawnradius2", self.spawnradius2),
+        ];
+        for &(name, value) in checks.iter() {
+            if value <= 0 {
+                return Err(Error::InvalidParameter(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the given line for parameter commands and update self.
+    ///
+    /// Unknown parameters are stored in `extra` rather than refused.
+    ///
+    /// Errors are wrapped in `Error::Parse` with the offending line.
     pub fn update(&mut self, line: &str) -> Result<()> {
+        self.parse_line(line).map_err(|e| Error::parse(0, line, e))
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
         let mut splitn = line.splitn(2, ' ');
         let var = try!(splitn.next().ok_or(Error::BadParameter));
         let val = try!(splitn.next().ok_or(Error::BadParameter));
@@ -61,7 +107,10 @@ impl Params {
             "attackradius2" => self.attackradius2 = try!(val.parse()),
             "spawnradius2" => self.spawnradius2 = try!(val.parse()),
             "player_seed" => self.player_seed = try!(val.parse()),
-            _ => return Err(Error::BadParameter),
+            "players" => self.players = try!(val.parse()),
+            _ => {
+                self.extra.insert(var.to_string(), val.to_string());
+            },
         }
         Ok(())
     }
@@ -71,6 +120,7 @@ impl Params {
 mod tests {
     use super::*;
     use std::default::Default;
+    use ants::error::Error;
 
     #[test]
     fn new() {
@@ -84,6 +134,8 @@ mod tests {
             attackradius2: 0i32,
             spawnradius2: 0i32,
             player_seed: 0i64,
+            players: 0i32,
+            extra: HashMap::new(),
         });
     }
 
@@ -99,6 +151,10 @@ mod tests {
         params.update("attackradius2 5").unwrap();
         params.update("spawnradius2 1").unwrap();
         params.update("player_seed 42").unwrap();
+        params.update("players 2").unwrap();
+        params.update("food_rate 5 11").unwrap();
+        let mut extra = HashMap::new();
+        extra.insert("food_rate".to_string(), "5 11".to_string());
         assert_eq!(params, Params {
             loadtime: 3000,
             turntime: 1000,
@@ -109,7 +165,30 @@ mod tests {
             attackradius2: 5,
             spawnradius2: 1,
             player_seed: 42,
+            players: 2,
+            extra: extra,
             ..Default::default()
         });
     }
+
+    #[test]
+    fn parse_error_context() {
+        let input = "turn 0\nrows 20\ncols x\nready\n";
+        match Params::from_buf_read(input.as_bytes()) {
+            Err(Error::Parse { turn: 0, line: Some(3), ref text, .. }) => {
+                assert_eq!(&**text, "cols x");
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let input = "turn 0\nrows 20\ncols 20\nviewradius2 55\n\
+                     attackradius2 5\nready\n";
+        match Params::from_buf_read(input.as_bytes()) {
+            Err(Error::InvalidParameter("spawnradius2")) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,199 @@
+//! A* pathfinding for single routes across the map.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use ants::direction::Direction;
+use ants::map::{Map, UnknownPolicy};
+use ants::point::Point;
+
+/// Find a shortest route from `from` to `to` with A*.
+///
+/// The heuristic is the wrap-aware manhattan distance, which never
+/// overestimates on the torus. Unseen tiles are treated according to
+/// `unknown`. Returns the moves to make in order, empty if `from` is `to`,
+/// or `None` if `to` cannot be reached.
+pub fn find_path(map: &Map, from: Point, to: Point, unknown: UnknownPolicy)
+        -> Option<Vec<Direction>> {
+    let (rows, cols) = (map.rows(), map.cols());
+    let from = from.wrap(rows, cols);
+    let to = to.wrap(rows, cols);
+    if from == to {
+        return Some(Vec::new());
+    }
+    if !map.is_walkable(to, unknown) {
+        return None;
+    }
+    a_star(from, to, |point| {
+        Direction::ALL.iter()
+            .map(|&d| ((point + d).wrap(rows, cols), 1, d))
+            .filter(|&(next, _, _)| map.is_walkable(next, unknown))
+            .collect()
+    }, |point| point.manhattan(to, rows, cols))
+}
+
+/// A* search from `from` to `to` over any graph of points.
+///
+/// `edges` gives the edges leaving a point, each as the point it leads to,
+/// its cost and a label, such as the moves which follow it. `estimate`
+/// gives the remaining cost from a point to `to`, and must never
+/// overestimate it. Returns the labels of the edges along a cheapest
+/// route, or `None` if `to` cannot be reached.
+pub fn a_star<E, F, H>(from: Point, to: Point, mut edges: F, estimate: H)
+        -> Option<Vec<E>>
+        where E: Clone, F: FnMut(Point) -> Vec<(Point, i32, E)>,
+              H: Fn(Point) -> i32 {
+    let mut costs = HashMap::new();
+    let mut came_from: HashMap<Point, (Point, E)> = HashMap::new();
+    // ordered by lowest estimate first, then by furthest along
+    let mut open = BinaryHeap::new();
+    costs.insert(from, 0);
+    open.push((Reverse(estimate(from)), 0, from));
+    while let Some((_, cost, point)) = open.pop() {
+        if point == to {
+            let mut labels = Vec::new();
+            let mut point = to;
+            while point != from {
+                let (previous, ref label) = came_from[&point];
+                labels.push(label.clone());
+                point = previous;
+            }
+            labels.reverse();
+            return Some(labels);
+        }
+        if costs.get(&point).map_or(false, |&best| cost > best) {
+            continue;
+        }
+        for (next, step, label) in edges(point) {
+            let next_cost = cost + step;
+            if costs.get(&next).map_or(true, |&best| next_cost < best) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, (point, label));
+                let guess = next_cost + estimate(next);
+                open.push((Reverse(guess), next_cost, next));
+            }
+        }
+    }
+    None
+}
+
+/// Whether every step of `path` from `from` is still walkable on `map`.
+pub fn is_path_clear(map: &Map, from: Point, path: &[Direction],
+                     unknown: UnknownPolicy) -> bool {
+    let mut point = from;
+    path.iter().all(|&direction| {
+        point = point + direction;
+        map.is_walkable(point, unknown)
+    })
+}
+
+/// An A* pathfinder which caches the routes it finds.
+///
+/// Routes are reused on later turns as long as they stay clear: a cached
+/// route is checked against the map before being returned, and searched
+/// again if water has since been discovered on it.
+#[derive(Debug, Clone)]
+pub struct Pathfinder {
+    unknown: UnknownPolicy,
+    cache: HashMap<(Point, Point), Vec<Direction>>,
+}
+
+impl Pathfinder {
+    /// Create a pathfinder with an empty cache, treating unseen tiles
+    /// according to `unknown`.
+    pub fn new(unknown: UnknownPolicy) -> Pathfinder {
+        Pathfinder {
+            unknown: unknown,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// A shortest route from `from` to `to`, from the cache if it is still
+    /// clear, otherwise found with `find_path` and cached.
+    pub fn find(&mut self, map: &Map, from: Point, to: Point)
+            -> Option<Vec<Direction>> {
+        let key = (from.wrap(map.rows(), map.cols()),
+                   to.wrap(map.rows(), map.cols()));
+        if let Some(path) = self.cache.get(&key) {
+            if is_path_clear(map, key.0, path, self.unknown) {
+                return Some(path.clone());
+            }
+        }
+        match find_path(map, key.0, key.1, self.unknown) {
+            Some(path) => {
+                self.cache.insert(key, path.clone());
+                Some(path)
+            },
+            None => {
+                self.cache.remove(&key);
+                None
+            },
+        }
+    }
+
+    /// The cached route from `from` to `to`, without checking whether it is
+    /// still clear. `map` is only used for its dimensions.
+    pub fn cached(&self, map: &Map, from: Point, to: Point)
+            -> Option<&[Direction]> {
+        let key = (from.wrap(map.rows(), map.cols()),
+                   to.wrap(map.rows(), map.cols()));
+        self.cache.get(&key).map(|path| &**path)
+    }
+
+    /// Drop every cached route which is no longer clear on `map`.
+    ///
+    /// Call this once per turn to keep the cache from holding stale routes.
+    pub fn revalidate(&mut self, map: &Map) {
+        let unknown = self.unknown;
+        self.cache.retain(|&(from, _), path| {
+            is_path_clear(map, from, path, unknown)
+        });
+    }
+
+    /// Drop every cached route.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Number of cached routes.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ants::tile::Tile;
+
+    #[test]
+    fn wraps() {
+        let map = Map::filled(10, 10, Tile::land());
+        let from = Point { row: 0, col: 0 };
+        let path = find_path(&map, from, Point { row: 9, col: 8 },
+                             UnknownPolicy::Blocked).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.iter().filter(|&&d| d == Direction::West).count(), 2);
+    }
+
+    #[test]
+    fn cache_revalidated() {
+        let mut map = Map::filled(5, 10, Tile::land());
+        let from = Point { row: 2, col: 0 };
+        let to = Point { row: 2, col: 2 };
+        let mut pathfinder = Pathfinder::new(UnknownPolicy::Passable);
+        let path = pathfinder.find(&map, from, to).unwrap();
+        assert_eq!(path, vec![Direction::East, Direction::East]);
+        map[Point { row: 2, col: 1 }] = Tile::water();
+        assert!(pathfinder.cached(&map, from, to).is_some());
+        // out of range points are wrapped as in `find`
+        let wrapped = Point { row: 7, col: 12 };
+        assert!(pathfinder.cached(&map, from, wrapped).is_some());
+        let path = pathfinder.find(&map, from, to).unwrap();
+        assert_eq!(path.len(), 4);
+        assert!(is_path_clear(&map, from, &path, UnknownPolicy::Passable));
+        map[Point { row: 1, col: 1 }] = Tile::water();
+        map[Point { row: 3, col: 1 }] = Tile::water();
+        pathfinder.revalidate(&map);
+        assert_eq!(pathfinder.len(), 0);
+    }
+}
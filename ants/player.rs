@@ -5,7 +5,7 @@ use std::str::FromStr;
 use ants::error::{Result, Error};
 
 /// One game player.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Player {
     /// Special value representing our own bot.
     Me,
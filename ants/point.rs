@@ -8,7 +8,7 @@ use ants::direction::Direction;
 ///
 /// The top left corner of the map is `Point { row: 0, col: 0 }`, with rows
 /// incrementing down and cols incrementing to the right.
-#[derive(Default, Hash, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Default, Hash, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Point {
     pub row: i32,
     pub col: i32,
@@ -16,12 +16,22 @@ pub struct Point {
 
 fn wrap(n: i32, max: i32) -> i32 {
     if n < 0 {
-        n % max + max
+        (n % max + max) % max
     } else {
         n % max
     }
 }
 
+/// The shortest signed step from `a` to `b` along one wrapping dimension.
+fn delta(a: i32, b: i32, max: i32) -> i32 {
+    let d = wrap(b - a, max);
+    if d * 2 > max {
+        d - max
+    } else {
+        d
+    }
+}
+
 impl Point {
     /// Calculate a point corrected for overflow or underflow.
     ///
@@ -34,6 +44,63 @@ impl Point {
             col: wrap(self.col, cols),
         }
     }
+
+    /// The shortest offset which takes `self` to `other` on the torus.
+    ///
+    /// Each component has the smallest magnitude possible, so that adding
+    /// the result to `self` and wrapping gives `other`.
+    pub fn delta(&self, other: Point, rows: i32, cols: i32) -> Point {
+        Point {
+            row: delta(self.row, other.row, rows),
+            col: delta(self.col, other.col, cols),
+        }
+    }
+
+    /// The squared euclidean distance to `other` on the torus.
+    ///
+    /// This is the measure used by the game for all of its radii.
+    pub fn distance2(&self, other: Point, rows: i32, cols: i32) -> i32 {
+        let Point{row, col} = self.delta(other, rows, cols);
+        row * row + col * col
+    }
+
+    /// The manhattan distance to `other` on the torus.
+    ///
+    /// On open land this is the number of moves an ant needs to get there.
+    pub fn manhattan(&self, other: Point, rows: i32, cols: i32) -> i32 {
+        let Point{row, col} = self.delta(other, rows, cols);
+        row.abs() + col.abs()
+    }
+
+    /// The four adjacent points, wrapped, in the order of `Direction::ALL`.
+    pub fn neighbors(&self, rows: i32, cols: i32) -> [Point; 4] {
+        Direction::ALL.map(|d| (*self + d).wrap(rows, cols))
+    }
+
+    /// The cardinal directions which bring `self` closer to `target` on the
+    /// torus.
+    ///
+    /// There is one for each axis `target` is off, and none when it is
+    /// `self`. When `target` is exactly half the map away along an axis,
+    /// either way round is as short, so both directions along it are given.
+    pub fn directions_toward(&self, target: Point, rows: i32, cols: i32)
+            -> Vec<Direction> {
+        let Point{row, col} = self.delta(target, rows, cols);
+        let mut directions = Vec::with_capacity(4);
+        if row < 0 || (row > 0 && row * 2 == rows) {
+            directions.push(Direction::North);
+        }
+        if row > 0 {
+            directions.push(Direction::South);
+        }
+        if col < 0 || (col > 0 && col * 2 == cols) {
+            directions.push(Direction::West);
+        }
+        if col > 0 {
+            directions.push(Direction::East);
+        }
+        directions
+    }
 }
 
 impl Add for Point {
@@ -70,4 +137,28 @@ mod tests {
         let p = Point{ row: 2, col: -1 };
         assert_eq!(p.wrap(2, 2), Point { row: 0, col: 1 });
     }
+
+    #[test]
+    fn distances() {
+        let a = Point { row: 0, col: 1 };
+        let b = Point { row: 9, col: 7 };
+        assert_eq!(a.delta(b, 10, 10), Point { row: -1, col: -4 });
+        assert_eq!(a.distance2(b, 10, 10), 17);
+        assert_eq!(a.manhattan(b, 10, 10), 5);
+        assert_eq!(a.manhattan(a, 10, 10), 0);
+    }
+
+    #[test]
+    fn directions_toward() {
+        let a = Point { row: 0, col: 0 };
+        assert_eq!(a.directions_toward(Point { row: 9, col: 2 }, 10, 10),
+                   vec![Direction::North, Direction::East]);
+        assert_eq!(a.directions_toward(Point { row: 0, col: 8 }, 10, 10),
+                   vec![Direction::West]);
+        assert_eq!(a.directions_toward(a, 10, 10), vec![]);
+        // half way round, both ways are as short
+        assert_eq!(a.directions_toward(Point { row: 1, col: 5 }, 10, 10),
+                   vec![Direction::South, Direction::West, Direction::East]);
+        assert_eq!(a.neighbors(10, 10)[0], Point { row: 9, col: 0 });
+    }
 }
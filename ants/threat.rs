@@ -0,0 +1,134 @@
+//! Threat and influence maps from attack ranges.
+
+use ants::grid::Grid;
+use ants::offsets::OffsetSet;
+use ants::point::Point;
+
+/// How dangerous a tile is to stand on next turn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Safety {
+    /// No enemy ant could attack the tile.
+    Safe,
+    /// Enemy ants could attack the tile, but no more of them than of ours.
+    Contested,
+    /// More enemy ants could attack the tile than ours could.
+    Deadly,
+}
+
+/// For every tile, how many ants of each side could attack it next turn.
+///
+/// Each ant reaches the tiles within `attackradius2` of wherever it could
+/// be after one move, as given by `World::attack_move_offsets`. Water in
+/// the way of that move is not taken into account.
+#[derive(Debug, Clone)]
+pub struct ThreatMap {
+    enemy: Grid<u16>,
+    own: Grid<u16>,
+    marks: Grid<usize>,
+}
+
+impl ThreatMap {
+    /// Create a map with no threats for a map with the given dimensions.
+    pub fn new(rows: i32, cols: i32) -> ThreatMap {
+        ThreatMap {
+            enemy: Grid::new(rows, cols, 0),
+            own: Grid::new(rows, cols, 0),
+            marks: Grid::new(rows, cols, 0),
+        }
+    }
+
+    /// Recount the threats from our ants at `ours` and the enemy's at
+    /// `enemies`, each reaching the tiles covered by `reach`.
+    pub fn update(&mut self, reach: &OffsetSet, ours: &[Point],
+                  enemies: &[Point]) {
+        self.enemy.fill(0);
+        self.own.fill(0);
+        self.marks.fill(0);
+        let mut mark = 0;
+        count(&mut self.own, &mut self.marks, &mut mark, reach, ours);
+        count(&mut self.enemy, &mut self.marks, &mut mark, reach, enemies);
+    }
+
+    /// How many enemy ants could attack `point` next turn.
+    pub fn enemy(&self, point: Point) -> u16 {
+        self.enemy[point]
+    }
+
+    /// How many of our ants could attack `point` next turn.
+    pub fn own(&self, point: Point) -> u16 {
+        self.own[point]
+    }
+
+    /// How dangerous `point` is, comparing the enemy's threat with ours.
+    pub fn safety(&self, point: Point) -> Safety {
+        let (enemy, own) = (self.enemy[point], self.own[point]);
+        if enemy == 0 {
+            Safety::Safe
+        } else if enemy <= own {
+            Safety::Contested
+        } else {
+            Safety::Deadly
+        }
+    }
+}
+
+/// Add one to `counts` on every tile within `reach` of each of `ants`.
+///
+/// The reach may overlap itself on small maps, so tiles are stamped in
+/// `marks` to count each ant only once.
+fn count(counts: &mut Grid<u16>, marks: &mut Grid<usize>, mark: &mut usize,
+         reach: &OffsetSet, ants: &[Point]) {
+    let (rows, cols) = (counts.rows(), counts.cols());
+    for &ant in ants {
+        *mark += 1;
+        for point in reach.around(ant, rows, cols) {
+            if marks[point] != *mark {
+                marks[point] = *mark;
+                counts[point] += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(row: i32, col: i32) -> Point {
+        Point { row: row, col: col }
+    }
+
+    #[test]
+    fn classifies() {
+        let reach = OffsetSet::new(1).expand();
+        let mut threat = ThreatMap::new(10, 10);
+        let ours = [point(5, 2)];
+        let enemies = [point(5, 6), point(4, 5)];
+        threat.update(&reach, &ours, &enemies);
+
+        assert_eq!(threat.enemy(point(5, 4)), 2);
+        assert_eq!(threat.own(point(5, 4)), 1);
+        assert_eq!(threat.safety(point(5, 4)), Safety::Deadly);
+        assert_eq!(threat.enemy(point(4, 4)), 1);
+        assert_eq!(threat.own(point(4, 4)), 0);
+        assert_eq!(threat.safety(point(5, 3)), Safety::Safe);
+        assert_eq!(threat.safety(point(0, 0)), Safety::Safe);
+
+        threat.update(&reach, &[point(5, 2), point(4, 3)], &enemies);
+        assert_eq!(threat.safety(point(5, 4)), Safety::Contested);
+        // enemies wrap round the edge
+        assert_eq!(threat.enemy(point(5, 9)), 0);
+        threat.update(&reach, &ours, &[point(5, 0)]);
+        assert_eq!(threat.enemy(point(5, 9)), 1);
+        assert_eq!(threat.enemy(point(5, 8)), 1);
+    }
+
+    #[test]
+    fn counts_each_ant_once() {
+        // the reach covers a tiny map more than once
+        let reach = OffsetSet::new(5).expand();
+        let mut threat = ThreatMap::new(3, 3);
+        threat.update(&reach, &[], &[point(1, 1)]);
+        assert_eq!(threat.enemy(point(0, 0)), 1);
+    }
+}
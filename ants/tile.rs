@@ -1,39 +1,91 @@
 //! Map tiles.
 
-use ants::ant::Ant;
+use std::default::Default;
 use ants::player::Player;
 
-/// One tile on the game map.
-///
-/// A tile has a given position indexed by a `Point` into a `Map`.
+/// What a tile is made of, which never changes during a game.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum Tile {
-    /// Is (and always will be) impassable water.
-    Water,
-    /// Currently contains food.
-    Food,
-    /// Currently contains an ant (or many ants, if all dead).
-    Ant(Ant),
-    /// Currently contains an ant hill owned by the given `Player`.
-    Hill(Player),
-    /// Currently just vacant land.
+pub enum Terrain {
+    /// Not known yet, as the tile has never been seen.
+    Unknown,
+    /// Land, which ants can cross.
     Land,
+    /// Impassable water.
+    Water,
+}
+
+impl Default for Terrain {
+    fn default() -> Terrain {
+        Terrain::Unknown
+    }
+}
+
+/// One tile on the game map.
+///
+/// A tile has a given position indexed by a `Point` into a `Map`. Several
+/// things can share a tile, e.g. an ant standing on a hill, so each is
+/// kept in its own layer. The default tile has never been seen.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    /// What the tile is made of.
+    pub terrain: Terrain,
+    /// Whether the tile currently contains food.
+    pub food: bool,
+    /// Owner of the ant hill on this tile, if any.
+    pub hill: Option<Player>,
+    /// Owner of the live ant on this tile, if any.
+    pub ant: Option<Player>,
+    /// Owner of the dead ants on this tile, if any.
+    ///
+    /// Dead ants can stack, so this can represent any number of them; this
+    /// level of detail is typically not important.
+    pub dead: Option<Player>,
 }
 
 impl Tile {
+    /// A tile of vacant land.
+    pub fn land() -> Tile {
+        Tile {
+            terrain: Terrain::Land,
+            ..Default::default()
+        }
+    }
+
+    /// A tile of water.
+    pub fn water() -> Tile {
+        Tile {
+            terrain: Terrain::Water,
+            ..Default::default()
+        }
+    }
+
     /// Whether the tile can be crossed by an ant (or could be at some point).
     pub fn is_passable(&self) -> bool {
-        match *self {
-            Tile::Water => false,
-            _ => true,
-        }
+        self.terrain != Terrain::Water
     }
 
     /// Whether the tile has nothing on it (except maybe dead ants).
     pub fn is_unoccupied(&self) -> bool {
-        match *self {
-            Tile::Land | Tile::Ant(Ant { alive: false, .. }) => true,
-            _ => false,
-        }
+        self.terrain == Terrain::Land && !self.food && self.hill.is_none() &&
+            self.ant.is_none()
+    }
+
+    /// Whether a live ant owned by `player` is on the tile.
+    pub fn has_ant_of(&self, player: Player) -> bool {
+        self.ant == Some(player)
+    }
+
+    /// Whether a hill owned by `player` is on the tile.
+    pub fn has_hill_of(&self, player: Player) -> bool {
+        self.hill == Some(player)
+    }
+
+    /// Clear everything on the tile which changes from turn to turn,
+    /// leaving only the terrain.
+    pub fn clear(&mut self) {
+        *self = Tile {
+            terrain: self.terrain,
+            ..Default::default()
+        };
     }
 }
@@ -1,13 +1,24 @@
 //! The entire game world.
 
-use ants::tile::Tile;
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use ants::tile::{Tile, Terrain};
 use ants::point::Point;
-use ants::ant::Ant;
 use ants::params::Params;
 use ants::error::{Result, Error};
-use ants::map::Map;
+use ants::map::{Map, UnknownPolicy};
 use ants::direction::Direction;
 use ants::player::Player;
+use ants::order::{Order, OrderError};
+use ants::clock::Clock;
+use ants::memory::Memory;
+use ants::grid::Grid;
+use ants::offsets::OffsetSet;
+use ants::threat::ThreatMap;
+use ants::moves;
 
 /// The entire game world.
 ///
@@ -18,65 +29,168 @@ pub struct World<'a> {
     params: &'a Params,
     turn: i32,
     pub map: Map,
-    vision_offsets: Box<[Point]>,
+    visible: Grid<bool>,
+    explored: Grid<bool>,
+    visible_count: usize,
+    entities: Entities,
+    memory: Memory,
+    view: OffsetSet,
+    attack: OffsetSet,
+    attack_move: OffsetSet,
+    spawn: OffsetSet,
+    threat: ThreatMap,
+    orders: Vec<Order>,
+    watch: Option<Arc<Mutex<Vec<Order>>>>,
+    strict: bool,
+    clock: Clock,
+    recovered: Vec<Error>,
+    output: Output<'a>,
 }
 
-/// Calculate a set of "vision offsets": points relative to an ant that it
-/// can see. This is an expensive operation, and so should be performed once
-/// and cached (as the results are valid for the entirety of a game).
-fn vision_offsets(params: &Params) -> Box<[Point]> {
-    let mut offsets = Vec::new();
-    let mx = (params.viewradius2 as f64).sqrt() as i32;
-    for d_row in -mx..mx+1 {
-        for d_col in -mx..mx+1 {
-            let d = d_row * d_row + d_col * d_col;
-            if 0 < d && d <= params.viewradius2 {
-                offsets.push(Point {
-                    row: d_row,
-                    col: d_col,
-                });
-            }
-        }
+/// Points of everything reported this turn, indexed as input is read.
+#[derive(Default, Debug)]
+struct Entities {
+    ants: HashMap<Player, Vec<Point>>,
+    enemy_ants: Vec<Point>,
+    dead_ants: Vec<Point>,
+    food: Vec<Point>,
+    my_hills: Vec<Point>,
+    enemy_hills: Vec<Point>,
+}
+
+/// Where orders are written, wrapped so that `World` can still be `Debug`.
+struct Output<'a>(Box<dyn Write + 'a>);
+
+impl<'a> fmt::Debug for Output<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Output")
     }
-    offsets.into_boxed_slice()
+}
+
+/// Convert a time given by the engine in milliseconds into a `Duration`.
+fn millis(ms: i32) -> Duration {
+    Duration::from_millis(if ms > 0 { ms as u64 } else { 0 })
 }
 
 impl<'a> World<'a> {
     /// Construct a new `World` from the given game parameters.
-    pub fn new(params: &Params) -> World {
+    ///
+    /// Orders and the end of each turn are written to `output`. The clock
+    /// starts immediately, with `loadtime` as its budget.
+    pub fn new<W: Write + 'a>(params: &'a Params, output: W)
+            -> World<'a> {
+        let attack = OffsetSet::new(params.attackradius2);
         World {
             params: params,
             turn: 0,
             map: Map::new(params.rows, params.cols),
-            vision_offsets: vision_offsets(params),
+            visible: Grid::new(params.rows, params.cols, false),
+            explored: Grid::new(params.rows, params.cols, false),
+            visible_count: 0,
+            entities: Default::default(),
+            memory: Memory::new(params.rows, params.cols),
+            view: OffsetSet::new(params.viewradius2),
+            attack_move: attack.expand(),
+            attack: attack,
+            spawn: OffsetSet::new(params.spawnradius2),
+            threat: ThreatMap::new(params.rows, params.cols),
+            orders: Vec::new(),
+            watch: None,
+            strict: false,
+            clock: Clock::new(millis(params.loadtime)),
+            recovered: Vec::new(),
+            output: Output(Box::new(output)),
         }
     }
 
-    /// Clear all tiles, except water (which never changes between turns).
+    /// Clear all tiles and visibility, except terrain (which never changes
+    /// between turns).
     pub fn clear(&mut self) {
         for (_, tile) in self.map.tiles_mut() {
-            if *tile != Some(Tile::Water) {
-                *tile = None;
-            }
+            tile.clear();
         }
+        self.visible.fill(false);
+        self.visible_count = 0;
+        self.entities = Default::default();
     }
 
-    /// Update vision relative to the given `point` using `vision_offsets`.
+    /// Update vision relative to the given `point` using the view offsets.
+    ///
+    /// Newly seen tiles are land unless the engine reports water on them.
     fn update_vision(&mut self, point: Point) {
-        for offset in self.vision_offsets.iter() {
+        for offset in self.view.offsets().iter() {
             let visible = point + *offset;
             // only update visibility of tiles which are not already visible
-            if let None = self.map[visible] {
-                self.map[visible] = Some(Tile::Land);
+            if !self.visible[visible] {
+                self.visible[visible] = true;
+                self.explored[visible] = true;
+                self.visible_count += 1;
+                if self.map[visible].terrain == Terrain::Unknown {
+                    self.map[visible].terrain = Terrain::Land;
+                }
             }
         }
     }
 
+    /// Whether the tile at `point` is seen by one of our ants this turn.
+    pub fn is_visible(&self, point: Point) -> bool {
+        self.visible[point]
+    }
+
+    /// Whether the tile at `point` has been seen on this or any earlier turn.
+    pub fn ever_seen(&self, point: Point) -> bool {
+        self.explored[point]
+    }
+
+    /// Number of tiles seen by our ants this turn.
+    pub fn visible_count(&self) -> usize {
+        self.visible_count
+    }
+
+    /// Offsets within `viewradius2` of an ant: the tiles it can see.
+    pub fn view_offsets(&self) -> &OffsetSet {
+        &self.view
+    }
+
+    /// Offsets within `attackradius2` of an ant: the tiles it attacks.
+    pub fn attack_offsets(&self) -> &OffsetSet {
+        &self.attack
+    }
+
+    /// Offsets within `attackradius2` of an ant after one more move: the
+    /// tiles it could attack next turn.
+    pub fn attack_move_offsets(&self) -> &OffsetSet {
+        &self.attack_move
+    }
+
+    /// How many ants of each side could attack each tile next turn, as of
+    /// the last `begin_turn`.
+    pub fn threat(&self) -> &ThreatMap {
+        &self.threat
+    }
+
+    /// Offsets within `spawnradius2` of an ant: the tiles it collects food
+    /// from.
+    pub fn spawn_offsets(&self) -> &OffsetSet {
+        &self.spawn
+    }
+
     /// Update world from one line of input.
     ///
     /// Blank and "go" lines are not handled here and should not be passed in.
     /// Vision is updated automatically as ants are discovered.
+    ///
+    /// Errors are wrapped in `Error::Parse` with the current turn and the
+    /// offending line; the caller reading the input can add the line number
+    /// with `Error::at_line`.
     pub fn update(&mut self, line: &str) -> Result<()> {
+        match self.parse_line(line) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(Error::parse(self.turn, line, e)),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<()> {
         let mut split = line.split(' ');
         let variant = try!(split.next().ok_or(Error::UnexpectedLine));
         if variant == "turn" {
@@ -89,27 +203,51 @@ impl<'a> World<'a> {
                 row: try!(row.parse()),
                 col: try!(col.parse()),
             };
-            let tile = match variant {
-                "w" => Some(Tile::Water),
-                "f" => Some(Tile::Food),
+            let point = point.wrap(self.params.rows, self.params.cols);
+            let mut tile = self.map[point];
+            let entities = &mut self.entities;
+            match variant {
+                "w" => tile.terrain = Terrain::Water,
+                "f" => {
+                    if !tile.food {
+                        entities.food.push(point);
+                    }
+                    tile.food = true;
+                },
                 _ => {
                     let owner = try!(split.next().ok_or(Error::UnknownCommand));
                     let owner = try!(owner.parse());
                     match variant {
-                        "h" => Some(Tile::Hill(owner)),
-                        "a" => Some(Tile::Ant(Ant {
-                            alive: true,
-                            owner: owner
-                        })),
-                        "d" => Some(Tile::Ant(Ant {
-                            alive: false,
-                            owner: owner
-                        })),
+                        "h" => {
+                            if tile.hill.is_none() {
+                                match owner {
+                                    Player::Me => entities.my_hills.push(point),
+                                    _ => entities.enemy_hills.push(point),
+                                }
+                            }
+                            tile.hill = Some(owner);
+                        },
+                        "a" => {
+                            if tile.ant.is_none() {
+                                entities.ants.entry(owner).or_insert(Vec::new())
+                                        .push(point);
+                                if owner != Player::Me {
+                                    entities.enemy_ants.push(point);
+                                }
+                            }
+                            tile.ant = Some(owner);
+                        },
+                        "d" => {
+                            if tile.dead.is_none() {
+                                entities.dead_ants.push(point);
+                            }
+                            tile.dead = Some(owner);
+                        },
                         _ => return Err(Error::UnknownCommand),
                     }
                 },
-            };
-            if let Some(Tile::Ant(Ant { owner: Player::Me, .. })) = tile {
+            }
+            if tile.has_ant_of(Player::Me) {
                 self.update_vision(point);
             }
             self.map[point] = tile;
@@ -117,13 +255,465 @@ impl<'a> World<'a> {
         Ok(())
     }
 
+    /// Prepare for `do_turn` once all input for the turn has been read.
+    ///
+    /// This restarts the clock with `turntime` as its budget, commits what
+    /// is visible to memory and counts the threats to each tile. It is
+    /// called by `Bot::run_with` when the "go" line arrives.
+    pub fn begin_turn(&mut self) {
+        self.begin_turn_at(Instant::now());
+    }
+
+    /// Like `begin_turn`, but with the clock started at `start`.
+    ///
+    /// Used when the "go" line was read some time before the turn could
+    /// begin, so that the clock still runs against the engine's deadline.
+    pub fn begin_turn_at(&mut self, start: Instant) {
+        self.clock.restart_at(start, millis(self.params.turntime));
+        self.memory.remember(&self.map, &self.visible, self.turn);
+        let ours = self.entities.ants.get(&Player::Me)
+            .map_or(&[][..], |ants| &ants[..]);
+        self.threat.update(&self.attack_move, ours, &self.entities.enemy_ants);
+    }
+
+    /// Points of our own live ants this turn.
+    pub fn my_ants(&self) -> &[Point] {
+        self.ants_of(Player::Me)
+    }
+
+    /// Points of all live enemy ants visible this turn.
+    pub fn enemy_ants(&self) -> &[Point] {
+        &self.entities.enemy_ants
+    }
+
+    /// Points of the live ants owned by `player` visible this turn.
+    pub fn ants_of(&self, player: Player) -> &[Point] {
+        match self.entities.ants.get(&player) {
+            Some(ants) => ants,
+            None => &[],
+        }
+    }
+
+    /// Points of all tiles with dead ants visible this turn.
+    pub fn dead_ants(&self) -> &[Point] {
+        &self.entities.dead_ants
+    }
+
+    /// Points of all food visible this turn.
+    pub fn food(&self) -> &[Point] {
+        &self.entities.food
+    }
+
+    /// Points of our own hills visible this turn.
+    pub fn my_hills(&self) -> &[Point] {
+        &self.entities.my_hills
+    }
+
+    /// Points of all enemy hills visible this turn.
+    pub fn enemy_hills(&self) -> &[Point] {
+        &self.entities.enemy_hills
+    }
+
+    /// What the bot remembers from this and earlier turns.
+    ///
+    /// Unlike `map`, which only holds what is visible this turn, memory
+    /// keeps the last known contents of every tile ever seen.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Time keeping for the current phase of the game.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Mutable time keeping for the current phase, e.g. to set the margin.
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    /// Record an error which the bot recovered from, for review later.
+    ///
+    /// This is used by `Bot::run_resilient_with`.
+    pub fn record_error(&mut self, error: Error) {
+        self.recovered.push(error);
+    }
+
+    /// All errors recovered from so far this game, oldest first.
+    pub fn recovered_errors(&self) -> &[Error] {
+        &self.recovered
+    }
+
     /// Check what turn it is.
     pub fn turn(&self) -> i32 {
         self.turn
     }
 
-    /// Issue order for an ant at a given point to move in the given direction.
-    pub fn order(&self, point: Point, direction: Direction) {
-        println!("o {} {}", point, direction);
+    /// Queue an order for the ant at `point` to move in `direction`.
+    ///
+    /// Orders are only sent to the engine once the turn is over, so until
+    /// then they can be inspected with `orders`, or revised with
+    /// `replace_order` and `cancel_order`.
+    ///
+    /// The order is refused if there is no live ant of ours at `point`, if
+    /// the ant would walk into water, or if the ant was already ordered this
+    /// turn. In strict mode it is also refused if another of our ants was
+    /// already ordered onto the same tile, or stands there with no order to
+    /// move away.
+    pub fn order(&mut self, point: Point, direction: Direction)
+            -> ::std::result::Result<(), OrderError> {
+        let order = Order {
+            from: point.wrap(self.params.rows, self.params.cols),
+            direction: direction,
+        };
+        if self.order_for(order.from).is_some() {
+            return Err(OrderError::Duplicate);
+        }
+        try!(self.validate(&order, &self.orders));
+        self.orders.push(order);
+        self.publish();
+        Ok(())
+    }
+
+    /// Check an order against the map and, in strict mode, the other orders
+    /// in `orders`, normally those queued this turn.
+    fn validate(&self, order: &Order, orders: &[Order])
+            -> ::std::result::Result<(), OrderError> {
+        if !self.map[order.from].has_ant_of(Player::Me) {
+            return Err(OrderError::NoAnt);
+        }
+        if !self.map[order.to()].is_passable() {
+            return Err(OrderError::Water);
+        }
+        if self.strict {
+            let (rows, cols) = (self.params.rows, self.params.cols);
+            let to = order.to().wrap(rows, cols);
+            // an ant of ours with no order stays put and holds its tile
+            let held = self.map[to].has_ant_of(Player::Me) &&
+                orders.iter().all(|other| other.from != to);
+            if held || orders.iter().any(|other| {
+                other.from != order.from && other.to().wrap(rows, cols) == to
+            }) {
+                return Err(OrderError::Collision);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether colliding orders are refused.
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Set whether orders moving two of our ants onto the same tile are
+    /// refused. Under the game rules such a collision kills both ants.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// All orders queued so far this turn, in the order they were issued.
+    pub fn orders(&self) -> &[Order] {
+        &self.orders
+    }
+
+    /// The order queued for the ant at `point`, if any.
+    pub fn order_for(&self, point: Point) -> Option<&Order> {
+        let from = point.wrap(self.params.rows, self.params.cols);
+        self.orders.iter().find(|order| order.from == from)
+    }
+
+    /// Change the direction of the order for the ant at `point`.
+    ///
+    /// If no order was queued for the ant, one is queued now. Returns the
+    /// order which was replaced, if any. The new order is validated as in
+    /// `order`; if it is refused, the old order is kept.
+    pub fn replace_order(&mut self, point: Point, direction: Direction)
+            -> ::std::result::Result<Option<Order>, OrderError> {
+        let order = Order {
+            from: point.wrap(self.params.rows, self.params.cols),
+            direction: direction,
+        };
+        try!(self.validate(&order, &self.orders));
+        match self.orders.iter().position(|o| o.from == order.from) {
+            Some(i) => {
+                let old = self.orders[i];
+                self.orders[i] = order;
+                self.publish();
+                Ok(Some(old))
+            },
+            None => {
+                self.orders.push(order);
+                self.publish();
+                Ok(None)
+            },
+        }
+    }
+
+    /// Remove the order for the ant at `point`, returning it if there was one.
+    pub fn cancel_order(&mut self, point: Point) -> Option<Order> {
+        let from = point.wrap(self.params.rows, self.params.cols);
+        match self.orders.iter().position(|order| order.from == from) {
+            Some(i) => {
+                let order = self.orders.remove(i);
+                self.publish();
+                Some(order)
+            },
+            None => None,
+        }
+    }
+
+    /// Order each ant in `moves` by its preferred directions, best first,
+    /// so that none of them collide.
+    ///
+    /// Any orders already queued for these ants are replaced, and ants
+    /// which cannot move without a collision have their orders cancelled;
+    /// see `moves::resolve`. Our other ants keep their squares, or the
+    /// squares they were already ordered onto, and are not moved onto.
+    ///
+    /// The new orders are validated as in `order`, and if any is refused no
+    /// orders are changed. The same goes, even outside strict mode, for an
+    /// ant in `moves` which has to stay on a square another ant was already
+    /// ordered onto; the error is then `OrderError::Collision`.
+    pub fn order_moves(&mut self, moves: &[(Point, Vec<Direction>)])
+            -> ::std::result::Result<(), OrderError> {
+        let (rows, cols) = (self.params.rows, self.params.cols);
+        let listed: HashSet<Point> = moves.iter()
+            .map(|&(point, _)| point.wrap(rows, cols))
+            .collect();
+        if listed.len() < moves.len() {
+            return Err(OrderError::Duplicate);
+        }
+        let held: Vec<Point> = self.my_ants().iter()
+            .filter(|ant| !listed.contains(ant))
+            .map(|&ant| match self.order_for(ant) {
+                Some(order) => order.to().wrap(rows, cols),
+                None => ant,
+            })
+            .collect();
+        let mut orders: Vec<Order> = self.orders.iter()
+            .filter(|order| !listed.contains(&order.from))
+            .cloned()
+            .collect();
+        let resolved = moves::resolve(&self.map, moves, &held,
+                                      UnknownPolicy::Passable);
+        // an ant with nowhere to go would be walked into
+        if listed.iter().any(|ant| {
+            held.contains(ant) &&
+                resolved.iter().all(|order| order.from != *ant)
+        }) {
+            return Err(OrderError::Collision);
+        }
+        orders.extend(resolved);
+        for order in &orders {
+            try!(self.validate(order, &orders));
+        }
+        self.orders = orders;
+        self.publish();
+        Ok(())
+    }
+
+    /// Drop all orders queued this turn without sending them.
+    pub fn discard_orders(&mut self) {
+        self.orders.clear();
+        self.publish();
+    }
+
+    /// Share a copy of the order queue, kept up to date as orders change.
+    ///
+    /// This lets another thread see the orders issued so far while
+    /// `do_turn` is still running; it is used by `Bot::run_guarded_with`.
+    pub fn watch_orders(&mut self) -> Arc<Mutex<Vec<Order>>> {
+        let watch = Arc::new(Mutex::new(self.orders.clone()));
+        self.watch = Some(watch.clone());
+        watch
+    }
+
+    /// Stop sharing the order queue.
+    pub fn unwatch_orders(&mut self) {
+        self.watch = None;
+    }
+
+    /// Copy the order queue to the watcher, if there is one.
+    fn publish(&self) {
+        if let Some(ref watch) = self.watch {
+            *watch.lock().unwrap() = self.orders.clone();
+        }
+    }
+
+    /// Send all queued orders and signal to the engine that the bot is done
+    /// with the current phase.
+    ///
+    /// This is called by `Bot::run_with` once `do_turn` returns, and should
+    /// not normally be called from `do_turn`.
+    pub fn go(&mut self) -> Result<()> {
+        for order in self.orders.drain(..) {
+            try!(writeln!(self.output.0, "{}", order));
+        }
+        self.publish();
+        try!(writeln!(self.output.0, "go"));
+        try!(self.output.0.flush());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use ants::threat::Safety;
+
+    fn params() -> Params {
+        let mut params = Params::new();
+        params.rows = 4;
+        params.cols = 4;
+        params.viewradius2 = 1;
+        params
+    }
+
+    #[test]
+    fn layered_tiles() {
+        let params = params();
+        let mut world = World::new(&params, io::sink());
+        world.update("h 1 1 1").unwrap();
+        world.update("a 1 1 0").unwrap();
+        world.update("d 1 1 1").unwrap();
+        let tile = world.map[Point { row: 1, col: 1 }];
+        assert!(tile.has_hill_of(Player::Other(1)));
+        assert!(tile.has_ant_of(Player::Me));
+        assert_eq!(tile.dead, Some(Player::Other(1)));
+        assert_eq!(world.my_ants(), &[Point { row: 1, col: 1 }]);
+        assert_eq!(world.enemy_hills(), &[Point { row: 1, col: 1 }]);
+        assert_eq!(world.ants_of(Player::Other(1)), &[]);
+        world.clear();
+        assert_eq!(world.my_ants(), &[]);
+    }
+
+    #[test]
+    fn visibility() {
+        let params = params();
+        let mut world = World::new(&params, io::sink());
+        let p = |row, col| Point { row: row, col: col };
+        world.update("w 1 2").unwrap();
+        world.update("a 1 1 0").unwrap();
+        assert_eq!(world.visible_count(), 5);
+        assert!(world.is_visible(p(0, 1)));
+        assert!(!world.is_visible(p(3, 3)));
+        assert_eq!(world.map[p(1, 0)].terrain, Terrain::Land);
+        assert_eq!(world.map[p(1, 2)].terrain, Terrain::Water);
+        assert_eq!(world.map[p(3, 3)].terrain, Terrain::Unknown);
+        world.clear();
+        assert_eq!(world.visible_count(), 0);
+        assert!(!world.is_visible(p(0, 1)));
+        assert!(world.ever_seen(p(0, 1)));
+        assert_eq!(world.map[p(1, 2)].terrain, Terrain::Water);
+    }
+
+    #[test]
+    fn threat() {
+        let mut params = params();
+        params.attackradius2 = 1;
+        let mut world = World::new(&params, io::sink());
+        world.update("a 0 0 0").unwrap();
+        world.update("a 2 2 1").unwrap();
+        world.begin_turn();
+        let p = |row, col| Point { row: row, col: col };
+        assert_eq!(world.threat().enemy(p(2, 0)), 1);
+        assert_eq!(world.threat().own(p(2, 0)), 1);
+        assert_eq!(world.threat().safety(p(2, 0)), Safety::Contested);
+        assert_eq!(world.threat().safety(p(3, 2)), Safety::Deadly);
+    }
+
+    #[test]
+    fn order_moves() {
+        let params = params();
+        let mut world = World::new(&params, io::sink());
+        world.update("a 1 1 0").unwrap();
+        world.update("a 1 2 0").unwrap();
+        world.update("a 2 2 0").unwrap();
+        let p = |row, col| Point { row: row, col: col };
+        world.order(p(2, 2), Direction::North).unwrap();
+        world.order_moves(&[
+            (p(1, 1), vec![Direction::East, Direction::South]),
+            (p(1, 2), vec![Direction::South]),
+            (p(2, 2), vec![Direction::West]),
+        ]).unwrap();
+        assert_eq!(world.orders(), &[
+            Order { from: p(1, 1), direction: Direction::East },
+            Order { from: p(1, 2), direction: Direction::South },
+            Order { from: p(2, 2), direction: Direction::West },
+        ]);
+    }
+
+    #[test]
+    fn order_moves_around_others() {
+        let params = params();
+        let mut world = World::new(&params, io::sink());
+        world.update("a 0 1 0").unwrap();
+        world.update("a 1 1 0").unwrap();
+        world.update("a 2 0 0").unwrap();
+        let p = |row, col| Point { row: row, col: col };
+        let (north, south) = (Direction::North, Direction::South);
+        let east = Direction::East;
+        world.order(p(2, 0), east).unwrap();
+        // 0,1 stays put and 2,1 is taken by the ant ordered there
+        world.order_moves(&[(p(1, 1), vec![north, south, east])]).unwrap();
+        let orders = [
+            Order { from: p(2, 0), direction: east },
+            Order { from: p(1, 1), direction: east },
+        ];
+        assert_eq!(world.orders(), &orders);
+        // a refused order leaves the queue as it was
+        assert_eq!(world.order_moves(&[
+            (p(1, 1), vec![south]),
+            (p(3, 0), vec![north]),
+        ]), Err(OrderError::NoAnt));
+        assert_eq!(world.orders(), &orders);
+
+        // an ant which cannot leave a square another ant is ordered onto
+        let mut world = World::new(&params, io::sink());
+        world.update("a 1 1 0").unwrap();
+        world.update("a 1 2 0").unwrap();
+        world.update("w 0 1").unwrap();
+        let west = Direction::West;
+        world.order(p(1, 2), west).unwrap();
+        assert_eq!(world.order_moves(&[(p(1, 1), vec![north])]),
+                   Err(OrderError::Collision));
+        assert_eq!(world.orders(), &[Order { from: p(1, 2), direction: west }]);
+        world.order_moves(&[(p(1, 1), vec![north, south])]).unwrap();
+        assert_eq!(world.orders(), &[
+            Order { from: p(1, 2), direction: west },
+            Order { from: p(1, 1), direction: south },
+        ]);
+    }
+
+    #[test]
+    fn order_validation() {
+        let params = params();
+        let mut world = World::new(&params, io::sink());
+        world.update("a 1 1 0").unwrap();
+        world.update("a 1 3 0").unwrap();
+        world.update("a 2 1 0").unwrap();
+        world.update("a 2 3 0").unwrap();
+        world.update("a 3 3 1").unwrap();
+        world.update("w 0 1").unwrap();
+        let p = |row, col| Point { row: row, col: col };
+        let (north, south) = (Direction::North, Direction::South);
+        let (east, west) = (Direction::East, Direction::West);
+        assert_eq!(world.order(p(1, 1), north), Err(OrderError::Water));
+        assert_eq!(world.order(p(3, 3), north), Err(OrderError::NoAnt));
+        assert_eq!(world.order(p(1, 1), east), Ok(()));
+        assert_eq!(world.order(p(1, 1), south), Err(OrderError::Duplicate));
+        assert_eq!(world.order(p(1, 3), west), Ok(()));
+        world.set_strict(true);
+        assert_eq!(world.replace_order(p(1, 3), east),
+                   Ok(Some(Order { from: p(1, 3), direction: west })));
+        assert_eq!(world.replace_order(p(1, 3), west),
+                   Err(OrderError::Collision));
+        // the ant at 2,1 has no order, so it stays put
+        assert_eq!(world.replace_order(p(1, 1), south),
+                   Err(OrderError::Collision));
+        // but the ant at 1,3 is moving away
+        assert_eq!(world.order(p(2, 3), north), Ok(()));
+        assert_eq!(world.orders().len(), 3);
     }
 }